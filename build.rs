@@ -1,4 +1,7 @@
 fn main() {
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("macos") {
+        return;
+    }
     println!("cargo:rustc-link-lib=framework=SkyLight");
     println!("cargo:rustc-link-search=framework=/System/Library/PrivateFrameworks");
 }
@@ -0,0 +1,414 @@
+use crate::Rect;
+use crate::geometry::partition_edge;
+use std::str::FromStr;
+
+/// Windows are never shrunk below this many pixels in either direction.
+const MIN_SIZE: i32 = 1;
+
+/// `expand` takes grids with at most this many parts, finer grids are of no use on any screen.
+pub const MAX_EXPAND_PARTS: i32 = 1000;
+
+/// A direction to move in, or the edge of a window on that side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    fn is_horizontal(self) -> bool {
+        matches!(self, Direction::Left | Direction::Right)
+    }
+
+    /// The extent of `area` along this direction.
+    fn length_of(self, area: &Rect) -> i32 {
+        if self.is_horizontal() {
+            area.width
+        } else {
+            area.height
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" | "l" => Ok(Direction::Left),
+            "right" | "r" => Ok(Direction::Right),
+            "up" | "top" | "u" | "t" => Ok(Direction::Up),
+            "down" | "bottom" | "d" | "b" => Ok(Direction::Down),
+            _ => Err(format!(
+                "unknown direction {:?}, use left, right, up (top) or down (bottom)",
+                s
+            )),
+        }
+    }
+}
+
+/// A distance, either in pixels or in cells of a grid laid over the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Amount {
+    Pixels(i32),
+    Cells { count: i32, parts: i32 },
+}
+
+impl Amount {
+    /// The amount in pixels along `length` pixels of screen.
+    fn to_pixels(self, length: i32) -> i32 {
+        match self {
+            Amount::Pixels(p) => p,
+            Amount::Cells { count, parts } => partition_edge(0, length, parts, count),
+        }
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+
+    /// Parses `20` or `20px` as pixels and `2/12` as two cells of a grid with 12 parts.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |s: &str| {
+            s.parse::<i32>()
+                .ok()
+                .filter(|v| *v >= 0)
+                .ok_or_else(|| format!("not a non-negative integer: {:?}", s))
+        };
+        match s.split_once('/') {
+            Some((count, parts)) => {
+                let parts = number(parts)?;
+                if parts == 0 {
+                    return Err("the grid needs at least 1 part".to_string());
+                }
+                let count = number(count)?;
+                if count > parts {
+                    return Err(format!(
+                        "{} cells are more than the {} parts of the grid",
+                        count, parts
+                    ));
+                }
+                Ok(Amount::Cells { count, parts })
+            }
+            None => Ok(Amount::Pixels(number(s.strip_suffix("px").unwrap_or(s))?)),
+        }
+    }
+}
+
+/// Parses the number of parts of the grid for `expand`, from 1 to `MAX_EXPAND_PARTS`.
+pub fn parse_parts(s: &str) -> Result<i32, String> {
+    s.parse::<i32>()
+        .ok()
+        .filter(|parts| (1..=MAX_EXPAND_PARTS).contains(parts))
+        .ok_or_else(|| {
+            format!(
+                "the grid needs 1 to {} parts, not {:?}",
+                MAX_EXPAND_PARTS, s
+            )
+        })
+}
+
+/// Moves `frame` by `amount` in `direction`, keeping its size, but not further than the edge of
+/// `area`.
+pub fn nudge(frame: &Rect, area: &Rect, direction: Direction, amount: Amount) -> Rect {
+    let pixels = amount.to_pixels(direction.length_of(area));
+    let mut rv = frame.clone();
+    match direction {
+        Direction::Left => rv.x = frame.x.saturating_sub(pixels).max(area.x.min(frame.x)),
+        Direction::Up => rv.y = frame.y.saturating_sub(pixels).max(area.y.min(frame.y)),
+        Direction::Right => {
+            rv.x = frame
+                .x
+                .saturating_add(pixels)
+                .min((area.x + area.width - frame.width).max(frame.x))
+        }
+        Direction::Down => {
+            rv.y = frame
+                .y
+                .saturating_add(pixels)
+                .min((area.y + area.height - frame.height).max(frame.y))
+        }
+    }
+    rv
+}
+
+/// Moves the `edge` of `frame` outwards by `amount`, but not beyond the edge of `area`.
+pub fn grow(frame: &Rect, area: &Rect, edge: Direction, amount: Amount) -> Rect {
+    let pixels = amount.to_pixels(edge.length_of(area));
+    let (left, top, right, bottom) = edges(frame);
+    match edge {
+        Direction::Left => with_edge(
+            frame,
+            edge,
+            left.saturating_sub(pixels).max(area.x.min(left)),
+        ),
+        Direction::Up => with_edge(frame, edge, top.saturating_sub(pixels).max(area.y.min(top))),
+        Direction::Right => with_edge(
+            frame,
+            edge,
+            right
+                .saturating_add(pixels)
+                .min((area.x + area.width).max(right)),
+        ),
+        Direction::Down => with_edge(
+            frame,
+            edge,
+            bottom
+                .saturating_add(pixels)
+                .min((area.y + area.height).max(bottom)),
+        ),
+    }
+}
+
+/// Moves the `edge` of `frame` inwards by `amount`, keeping at least `MIN_SIZE` pixels.
+pub fn shrink(frame: &Rect, area: &Rect, edge: Direction, amount: Amount) -> Rect {
+    let pixels = amount.to_pixels(edge.length_of(area));
+    let (left, top, right, bottom) = edges(frame);
+    let position = match edge {
+        Direction::Left => left.saturating_add(pixels).min(right - MIN_SIZE),
+        Direction::Up => top.saturating_add(pixels).min(bottom - MIN_SIZE),
+        Direction::Right => right.saturating_sub(pixels).max(left + MIN_SIZE),
+        Direction::Down => bottom.saturating_sub(pixels).max(top + MIN_SIZE),
+    };
+    with_edge(frame, edge, position)
+}
+
+/// Moves the `edge` of `frame` outwards to the next line of a grid with `parts` parts laid over
+/// `area`. Like tiles, edges stop `gap` pixels short of inner grid lines.
+pub fn expand(frame: &Rect, area: &Rect, edge: Direction, parts: i32, gap: i32) -> Rect {
+    let leading = gap / 2;
+    let trailing = gap - leading;
+    let (origin, length) = if edge.is_horizontal() {
+        (area.x, area.width)
+    } else {
+        (area.y, area.height)
+    };
+    let (left, top, right, bottom) = edges(frame);
+    let lines = (0..=parts).map(|k| (k, partition_edge(origin, length, parts, k)));
+    let position = match edge {
+        Direction::Left | Direction::Up => {
+            let current = if edge == Direction::Left { left } else { top };
+            lines
+                .map(|(k, line)| if k == 0 { line } else { line + leading })
+                .filter(|p| *p < current)
+                .max()
+                .unwrap_or(current)
+        }
+        Direction::Right | Direction::Down => {
+            let current = if edge == Direction::Right {
+                right
+            } else {
+                bottom
+            };
+            lines
+                .map(|(k, line)| if k == parts { line } else { line - trailing })
+                .filter(|p| *p > current)
+                .min()
+                .unwrap_or(current)
+        }
+    };
+    with_edge(frame, edge, position)
+}
+
+/// Returns the left, top, right and bottom edge of `frame`.
+fn edges(frame: &Rect) -> (i32, i32, i32, i32) {
+    (
+        frame.x,
+        frame.y,
+        frame.x + frame.width,
+        frame.y + frame.height,
+    )
+}
+
+/// Returns `frame` with its `edge` moved to `position`, keeping the opposite edge in place.
+fn with_edge(frame: &Rect, edge: Direction, position: i32) -> Rect {
+    let (left, top, right, bottom) = edges(frame);
+    let (left, top, right, bottom) = match edge {
+        Direction::Left => (position, top, right, bottom),
+        Direction::Up => (left, position, right, bottom),
+        Direction::Right => (left, top, position, bottom),
+        Direction::Down => (left, top, right, position),
+    };
+    Rect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 1000,
+        height: 800,
+    };
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn nudge_stops_at_each_edge() {
+        let frame = rect(10, 20, 100, 100);
+        let nudge = |direction, pixels| nudge(&frame, &AREA, direction, Amount::Pixels(pixels));
+        assert_eq!(nudge(Direction::Left, 5), rect(5, 20, 100, 100));
+        assert_eq!(nudge(Direction::Left, 50), rect(0, 20, 100, 100));
+        assert_eq!(nudge(Direction::Up, 50), rect(10, 0, 100, 100));
+        assert_eq!(nudge(Direction::Right, 5000), rect(900, 20, 100, 100));
+        assert_eq!(nudge(Direction::Down, 5000), rect(10, 700, 100, 100));
+        assert_eq!(nudge(Direction::Left, i32::MAX), rect(0, 20, 100, 100));
+        assert_eq!(nudge(Direction::Down, i32::MAX), rect(10, 700, 100, 100));
+    }
+
+    #[test]
+    fn nudge_moves_in_cells_of_the_area() {
+        let frame = rect(10, 20, 100, 100);
+        let quarter = Amount::Cells { count: 1, parts: 4 };
+        assert_eq!(
+            nudge(&frame, &AREA, Direction::Right, quarter),
+            rect(260, 20, 100, 100)
+        );
+        assert_eq!(
+            nudge(&frame, &AREA, Direction::Down, quarter),
+            rect(10, 220, 100, 100)
+        );
+    }
+
+    #[test]
+    fn windows_outside_the_area_are_not_pulled_in() {
+        let outside = rect(-50, 20, 100, 100);
+        let pixels = Amount::Pixels(20);
+        assert_eq!(nudge(&outside, &AREA, Direction::Left, pixels), outside);
+        assert_eq!(
+            nudge(&outside, &AREA, Direction::Right, pixels),
+            rect(-30, 20, 100, 100)
+        );
+        assert_eq!(grow(&outside, &AREA, Direction::Left, pixels), outside);
+        let too_wide = rect(0, 20, 1200, 100);
+        assert_eq!(nudge(&too_wide, &AREA, Direction::Right, pixels), too_wide);
+        assert_eq!(grow(&too_wide, &AREA, Direction::Right, pixels), too_wide);
+    }
+
+    #[test]
+    fn grow_stops_at_each_edge() {
+        let frame = rect(100, 100, 200, 200);
+        let grow = |edge, pixels| grow(&frame, &AREA, edge, Amount::Pixels(pixels));
+        assert_eq!(grow(Direction::Left, 50), rect(50, 100, 250, 200));
+        assert_eq!(grow(Direction::Left, 500), rect(0, 100, 300, 200));
+        assert_eq!(grow(Direction::Up, 500), rect(100, 0, 200, 300));
+        assert_eq!(grow(Direction::Right, i32::MAX), rect(100, 100, 900, 200));
+        assert_eq!(grow(Direction::Down, i32::MAX), rect(100, 100, 200, 700));
+    }
+
+    #[test]
+    fn shrink_keeps_the_minimum_size() {
+        let frame = rect(100, 100, 200, 200);
+        let shrink = |edge, pixels| shrink(&frame, &AREA, edge, Amount::Pixels(pixels));
+        assert_eq!(shrink(Direction::Left, 50), rect(150, 100, 150, 200));
+        assert_eq!(shrink(Direction::Left, 500), rect(299, 100, MIN_SIZE, 200));
+        assert_eq!(
+            shrink(Direction::Up, i32::MAX),
+            rect(100, 299, 200, MIN_SIZE)
+        );
+        assert_eq!(
+            shrink(Direction::Right, i32::MAX),
+            rect(100, 100, MIN_SIZE, 200)
+        );
+        assert_eq!(shrink(Direction::Down, 500), rect(100, 100, 200, MIN_SIZE));
+    }
+
+    #[test]
+    fn expand_stops_short_of_inner_lines_by_the_gap() {
+        // Vertical lines of a grid with 4 parts are at 0, 250, 500, 750 and 1000, horizontal ones
+        // at 0, 200, 400, 600 and 800.
+        let expand = |frame: &Rect, edge| expand(frame, &AREA, edge, 4, 10);
+        let frame = rect(300, 0, 100, 800);
+        assert_eq!(expand(&frame, Direction::Right), rect(300, 0, 195, 800));
+        assert_eq!(expand(&frame, Direction::Left), rect(255, 0, 145, 800));
+        let tile = rect(255, 0, 240, 800);
+        assert_eq!(expand(&tile, Direction::Right), rect(255, 0, 490, 800));
+        assert_eq!(expand(&tile, Direction::Left), rect(0, 0, 495, 800));
+        let last = rect(755, 0, 245, 800);
+        assert_eq!(expand(&last, Direction::Right), last);
+        assert_eq!(expand(&last, Direction::Down), last);
+        assert_eq!(
+            expand(&rect(0, 100, 100, 100), Direction::Down),
+            rect(0, 100, 100, 295)
+        );
+    }
+
+    #[test]
+    fn parses_amounts() {
+        for (input, expected) in [
+            ("20", Ok(Amount::Pixels(20))),
+            ("20px", Ok(Amount::Pixels(20))),
+            ("0", Ok(Amount::Pixels(0))),
+            (
+                "2/12",
+                Ok(Amount::Cells {
+                    count: 2,
+                    parts: 12,
+                }),
+            ),
+            (
+                "12/12",
+                Ok(Amount::Cells {
+                    count: 12,
+                    parts: 12,
+                }),
+            ),
+        ] {
+            assert_eq!(input.parse::<Amount>(), expected, "{}", input);
+        }
+        for input in [
+            "",
+            "-1",
+            "20pt",
+            "2147483648",
+            "1/0",
+            "3/2",
+            "-1/2",
+            "1/-2",
+            "/2",
+        ] {
+            assert!(input.parse::<Amount>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn parses_directions() {
+        for (inputs, expected) in [
+            (&["left", "l"][..], Direction::Left),
+            (&["right", "r"], Direction::Right),
+            (&["up", "top", "u", "t"], Direction::Up),
+            (&["down", "bottom", "d", "b"], Direction::Down),
+        ] {
+            for input in inputs {
+                assert_eq!(input.parse(), Ok(expected), "{}", input);
+            }
+        }
+        assert!("sideways".parse::<Direction>().is_err());
+        assert!("Left".parse::<Direction>().is_err());
+    }
+
+    #[test]
+    fn expand_grids_are_bounded() {
+        assert_eq!(parse_parts("1"), Ok(1));
+        assert_eq!(parse_parts("1000"), Ok(MAX_EXPAND_PARTS));
+        for input in ["0", "-2", "1001", "2000000000", "twelve"] {
+            assert!(parse_parts(input).is_err(), "{}", input);
+        }
+    }
+}
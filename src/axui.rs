@@ -1,8 +1,9 @@
 use crate::Rect;
+use crate::backend::Window;
 use anyhow::{Result, bail};
 use cocoa::base::{id, nil};
 use core_foundation::{
-    array::CFArray,
+    array::{CFArray, CFArrayRef},
     base::{CFType, CFTypeRef, TCFType},
     boolean::CFBoolean,
     number::CFNumber,
@@ -16,19 +17,6 @@ use core_graphics::{
     geometry::{CGPoint, CGSize},
     window::kCGNullWindowID,
 };
-use serde::Serialize;
-
-#[derive(Debug, Serialize)]
-pub struct Window {
-    pub name: Option<String>,
-    pub owner_pid: i32,
-    pub layer: i32,
-    pub number: u32,
-    pub owner_name: String,
-    pub bounds: Rect,
-    pub alpha: f64,
-    pub on_screen: bool,
-}
 
 impl Window {
     fn from_cf_dict(dict: &CFDictionary<CFString, CFType>) -> Self {
@@ -100,26 +88,74 @@ pub fn check_accessibility_permission() -> bool {
     unsafe { AXIsProcessTrusted() }
 }
 
-fn frontmost_window_id(pid: i32) -> Result<id> {
+fn frontmost_window_id(pid: i32) -> Result<CFType> {
     unsafe {
-        // Create AXUIElement reference to the app
-        let ax_app = AXUIElementCreateApplication(pid);
-        if ax_app == nil {
-            bail!("Unable to create AXUIElement reference to front app.");
-        }
+        let ax_app = application_element(pid)?;
 
         // Get the focused window from the app
         let mut focused_window: id = nil;
         let result: i32 = AXUIElementCopyAttributeValue(
-            ax_app,
+            ax_app.as_CFTypeRef() as id,
             CFString::new("AXFocusedWindow").as_concrete_TypeRef() as CFTypeRef,
             &mut focused_window,
         );
         if result != 0 || focused_window == nil {
             bail!("Unable to retrieve focused window. Accessibility permission required!",);
         }
-        Ok(focused_window)
+        Ok(CFType::wrap_under_create_rule(focused_window as CFTypeRef))
+    }
+}
+
+/// Returns the Accessibility element of the app with the given `pid`, released when dropped.
+fn application_element(pid: i32) -> Result<CFType> {
+    let ax_app = unsafe { AXUIElementCreateApplication(pid) };
+    if ax_app == nil {
+        bail!("Unable to create AXUIElement reference to app.");
+    }
+    Ok(unsafe { CFType::wrap_under_create_rule(ax_app as CFTypeRef) })
+}
+
+/// Returns the window `number` of the app with the given `pid`, or its focused window if `number`
+/// is `None`. The Accessibility API does not know the numbers of the window list, so we look up
+/// the window's bounds there and take the app's window with the same frame. The element is
+/// released when dropped.
+pub fn window_element(pid: i32, number: Option<u32>) -> Result<CFType> {
+    let Some(number) = number else {
+        return frontmost_window_id(pid);
+    };
+    let Some(bounds) = window_list(true)
+        .into_iter()
+        .find(|w| w.owner_pid == pid && w.number == number)
+        .map(|w| w.bounds)
+    else {
+        bail!("Unknown window {} of pid {}.", number, pid);
+    };
+    unsafe {
+        let ax_app = application_element(pid)?;
+        let mut windows: id = nil;
+        let result: i32 = AXUIElementCopyAttributeValue(
+            ax_app.as_CFTypeRef() as id,
+            CFString::new("AXWindows").as_concrete_TypeRef() as CFTypeRef,
+            &mut windows,
+        );
+        if result != 0 || windows == nil {
+            bail!("Unable to retrieve windows of app. Accessibility permission required!");
+        }
+        let windows = CFArray::<CFType>::wrap_under_create_rule(windows as CFArrayRef);
+        for window in windows.iter() {
+            // Positions are fractional in the Accessibility API, but rounded in the window list.
+            let frame = window_frame(window.as_CFTypeRef() as id);
+            if frame.is_ok_and(|frame| frame.approx_eq(&bounds, 1)) {
+                // The array only lends its elements, this keeps ours alive after it is released.
+                return Ok((*window).clone());
+            }
+        }
     }
+    bail!(
+        "Window {} of pid {} is not accessible, it might be on another space.",
+        number,
+        pid
+    )
 }
 
 /// Fetch window list info and convert to Rust-friendly types.
@@ -219,38 +255,37 @@ fn set_window_pos(win: id, x: i32, y: i32) -> Result<()> {
     }
 }
 
-/// Moves and resizes the focused window of the app with the given `pid` to `rect` using the macOS Accessibility API (native).
-pub fn move_frontmost_window(pid: i32, rect: &Rect, order: Order) -> Result<()> {
-    let focused_window = frontmost_window_id(pid)?;
+/// Moves and resizes `win` to `rect` using the macOS Accessibility API (native).
+pub fn move_window(win: id, rect: &Rect, order: Order) -> Result<()> {
     match order {
         Order::MoveResize => {
-            set_window_pos(focused_window, rect.x, rect.y)?;
-            set_window_size(focused_window, rect.width, rect.height)?;
+            set_window_pos(win, rect.x, rect.y)?;
+            set_window_size(win, rect.width, rect.height)?;
         }
         Order::ResizeMove => {
-            set_window_size(focused_window, rect.width, rect.height)?;
-            set_window_pos(focused_window, rect.x, rect.y)?;
+            set_window_size(win, rect.width, rect.height)?;
+            set_window_pos(win, rect.x, rect.y)?;
         }
     }
     Ok(())
 }
 
-pub fn get_window_position_and_size(pid: i32) -> Result<Rect> {
+/// Returns the position and size of `win`.
+pub fn window_frame(win: id) -> Result<Rect> {
     unsafe {
-        let focused_window = frontmost_window_id(pid)?;
         let mut pos_ref: CFTypeRef = std::ptr::null();
         let mut size_ref: CFTypeRef = std::ptr::null();
 
         // Get AXPosition
         let result_pos = AXUIElementCopyAttributeValue(
-            focused_window,
+            win,
             CFString::new("AXPosition").as_concrete_TypeRef() as CFTypeRef,
             &mut pos_ref as *mut _ as *mut _,
         );
 
         // Get AXSize
         let result_size = AXUIElementCopyAttributeValue(
-            focused_window,
+            win,
             CFString::new("AXSize").as_concrete_TypeRef() as CFTypeRef,
             &mut size_ref as *mut _ as *mut _,
         );
@@ -0,0 +1,96 @@
+use crate::Rect;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A display as reported by a backend. All frames are in global coordinates with the origin at the
+/// top left of the primary screen, y going downwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Screen {
+    pub index: u64,
+    /// An identifier that stays the same when displays are reconnected, e.g. the display UUID.
+    #[serde(default)]
+    pub id: String,
+    /// A human readable name, e.g. the display model.
+    #[serde(default)]
+    pub name: String,
+    pub visible_frame: Rect,
+    pub frame: Rect,
+    /// Physical pixels per point, e.g. 2 for Retina displays.
+    #[serde(default = "default_scale_factor")]
+    pub scale_factor: f64,
+}
+
+fn default_scale_factor() -> f64 {
+    1.
+}
+
+/// Reads a description of displays from a JSON file. The file contains either a list of screens
+/// or an object with a `screens` key, like the fixtures of the fake backend.
+pub fn read_screens_file(path: &Path) -> Result<Vec<Screen>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ScreensFile {
+        List(Vec<Screen>),
+        Desktop { screens: Vec<Screen> },
+    }
+
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read screens file {}", path.display()))?;
+    let file: ScreensFile =
+        serde_json::from_str(&data).context("Failed to parse screens file as JSON")?;
+    Ok(match file {
+        ScreensFile::List(screens) | ScreensFile::Desktop { screens } => screens,
+    })
+}
+
+/// A top level window as reported by a backend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Window {
+    pub name: Option<String>,
+    pub owner_pid: i32,
+    pub layer: i32,
+    pub number: u32,
+    pub owner_name: String,
+    pub bounds: Rect,
+    pub alpha: f64,
+    pub on_screen: bool,
+}
+
+/// Identifies a window towards the backend that handed it out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowRef {
+    pub pid: i32,
+    /// The backend specific window number, if the backend knows it. A window without a number is
+    /// the focused window of the application with `pid`.
+    pub number: Option<u32>,
+}
+
+/// Everything `move_window` needs from the platform's window system.
+pub trait WindowBackend {
+    /// Returns all screens, ordered by their index.
+    fn screens(&self) -> Result<Vec<Screen>>;
+
+    /// Returns the window that currently has the keyboard focus.
+    fn focused_window(&self) -> Result<WindowRef>;
+
+    /// Returns the currently open windows. If `all_windows` is false, backends may filter out
+    /// windows that are not interesting to the user.
+    fn window_list(&self, all_windows: bool) -> Result<Vec<Window>>;
+
+    /// Returns the current frame of `window`.
+    fn get_frame(&self, window: &WindowRef) -> Result<Rect>;
+
+    /// Moves and resizes `window` to `frame`.
+    fn set_frame(&mut self, window: &WindowRef, frame: &Rect) -> Result<()>;
+
+    /// Returns the id of the currently active space (or workspace).
+    fn current_space_id(&self) -> Result<u64>;
+
+    /// Returns the name the window system itself gives the space with `id`, if any. Names set
+    /// with `set_space_name` take precedence.
+    fn space_name(&self, _id: u64) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
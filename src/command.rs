@@ -0,0 +1,1416 @@
+use crate::Rect;
+use crate::backend::Screen;
+use crate::error::Error;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScreenSelector {
+    /// The screen with this index.
+    Index(usize),
+    /// A screen picked by the selector character, see `error::SELECTOR_HELP`.
+    Char(char),
+    /// The n-th screen (counting from 0) in the direction of the selector character, e.g. `l1` is
+    /// the second screen from the left. Only available in the long form.
+    Nth(char, usize),
+    /// A user defined name for a screen from the config. Only available in the long form.
+    Alias(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveParameters {
+    pub screen: ScreenSelector,
+    pub x_ratio: i32,
+    pub y_ratio: i32,
+    /// The relative widths of the `x_ratio` columns, or empty if they are all equally wide.
+    pub x_weights: Vec<i32>,
+    /// The relative heights of the `y_ratio` rows, or empty if they are all equally high.
+    pub y_weights: Vec<i32>,
+    pub x_start: i32,
+    pub x_end: i32,
+    pub y_start: i32,
+    pub y_end: i32,
+}
+
+/// Where to put a window: cells of a grid, a fixed size at an anchor or a named area of the
+/// screen's template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placement {
+    Grid(MoveParameters),
+    Sized(SizedPlacement),
+    Area(AreaPlacement),
+}
+
+/// A named area of the template that the config defines for the screen, e.g. `1:term`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AreaPlacement {
+    pub screen: ScreenSelector,
+    pub name: String,
+}
+
+/// A window size in pixels or in percent of the screen, anchored to a point of the screen, e.g.
+/// `1:800x600` or `0:40%x70%@tr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizedPlacement {
+    pub screen: ScreenSelector,
+    pub width: Length,
+    pub height: Length,
+    pub anchor: Anchor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    Pixels(i32),
+    Percent(i32),
+}
+
+/// The point of the screen that a sized placement sticks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    const ALL: [(&'static str, Anchor); 9] = [
+        ("tl", Anchor::TopLeft),
+        ("t", Anchor::Top),
+        ("tr", Anchor::TopRight),
+        ("l", Anchor::Left),
+        ("c", Anchor::Center),
+        ("r", Anchor::Right),
+        ("bl", Anchor::BottomLeft),
+        ("b", Anchor::Bottom),
+        ("br", Anchor::BottomRight),
+    ];
+
+    /// Where a window sits along each axis: 0 at the start, 1 in the middle and 2 at the end.
+    pub fn alignment(self) -> (i32, i32) {
+        let idx = Anchor::ALL
+            .iter()
+            .position(|(_, a)| *a == self)
+            .expect("all anchors are listed.") as i32;
+        (idx % 3, idx / 3)
+    }
+}
+
+impl Placement {
+    /// Parses a placement command: an area if the part after the screen is a name, a sized
+    /// placement if it contains an `x` or a `%`, otherwise a grid command, see
+    /// `MoveParameters::from_command`.
+    pub fn from_command(s: &str) -> Result<Self, ParseError> {
+        if is_area_form(s) {
+            AreaPlacement::from_command(s).map(Placement::Area)
+        } else if is_sized_form(s) {
+            SizedPlacement::from_command(s).map(Placement::Sized)
+        } else {
+            MoveParameters::from_command(s).map(Placement::Grid)
+        }
+    }
+
+    pub fn screen(&self) -> &ScreenSelector {
+        match self {
+            Placement::Grid(params) => &params.screen,
+            Placement::Sized(sized) => &sized.screen,
+            Placement::Area(area) => &area.screen,
+        }
+    }
+}
+
+impl SizedPlacement {
+    /// Parses `screen:width[%]xheight[%][@anchor]`. The anchor defaults to the center.
+    pub fn from_command(s: &str) -> Result<Self, ParseError> {
+        let mut i = Parser::new(s);
+        let screen = i.screen()?;
+        if !i.separator()? {
+            return Err(i.unexpected(Expected::Separator));
+        }
+        let width = i.length()?;
+        match i.peek() {
+            Some('x') => {
+                i.next();
+            }
+            _ => return Err(i.unexpected(Expected::SizeSeparator)),
+        }
+        let height = i.length()?;
+        let anchor = match i.peek() {
+            None => Anchor::Center,
+            Some('@') => {
+                i.next();
+                let word = &i.input[i.pos..];
+                match Anchor::ALL.iter().find(|(name, _)| *name == word) {
+                    Some((_, anchor)) => *anchor,
+                    None => return Err(i.unexpected(Expected::Anchor)),
+                }
+            }
+            Some(_) => return Err(i.unexpected(Expected::End)),
+        };
+        Ok(SizedPlacement {
+            screen,
+            width,
+            height,
+            anchor,
+        })
+    }
+}
+
+impl AreaPlacement {
+    /// Parses `screen:area`. Area names consist of letters, digits, `-` and `_`.
+    pub fn from_command(s: &str) -> Result<Self, ParseError> {
+        let mut i = Parser::new(s);
+        let screen = i.screen()?;
+        if !i.separator()? {
+            return Err(i.unexpected(Expected::Separator));
+        }
+        let start = i.pos;
+        while i.peek().is_some_and(is_area_char) {
+            i.next();
+        }
+        if i.pos == start {
+            return Err(i.unexpected(Expected::AreaName));
+        }
+        if !i.is_done() {
+            return Err(i.unexpected(Expected::End));
+        }
+        Ok(AreaPlacement {
+            screen,
+            name: s[start..].to_string(),
+        })
+    }
+}
+
+/// Characters that can appear in the name of a template area.
+pub fn is_area_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+impl ScreenSelector {
+    /// Returns the screen from `screens` this selector refers to. `window` is the current frame of
+    /// the window to move, which the selectors relative to the window's screen need. `aliases` maps
+    /// user defined names to screen ids or names.
+    pub fn select<'a>(
+        &self,
+        screens: &'a [Screen],
+        window: Option<&Rect>,
+        aliases: &HashMap<String, String>,
+    ) -> Result<&'a Screen, Error> {
+        let get_screen_by_index = |index: usize| {
+            screens
+                .iter()
+                .find(|s| s.index == index as u64)
+                .ok_or_else(|| Error::UnknownScreen {
+                    index,
+                    screens: screens.to_vec(),
+                })
+        };
+        let (c, n) = match *self {
+            ScreenSelector::Index(index) => return get_screen_by_index(index),
+            ScreenSelector::Char(c) => (c, 0),
+            ScreenSelector::Nth(c, n) => (c, n),
+            ScreenSelector::Alias(ref alias) => {
+                let Some(target) = aliases.get(alias) else {
+                    return Err(Error::UnknownAlias {
+                        alias: alias.clone(),
+                        aliases: aliases.keys().cloned().collect(),
+                    });
+                };
+                return screens
+                    .iter()
+                    .find(|s| s.id == *target)
+                    .or_else(|| screens.iter().find(|s| s.name == *target))
+                    .ok_or_else(|| Error::AliasNotConnected {
+                        alias: alias.clone(),
+                        target: target.clone(),
+                        screens: screens.to_vec(),
+                    });
+            }
+        };
+        if screens.is_empty() {
+            return Err(Error::NoScreens);
+        }
+        let mut sorted = screens.iter().collect::<Vec<_>>();
+        match c {
+            'm' | 'c' if n == 0 => return get_screen_by_index(0),
+            'l' => sorted.sort_by_key(|s| (s.frame.x, s.frame.y)),
+            'r' => sorted.sort_by_key(|s| (-s.frame.x, s.frame.y)),
+            't' | 'u' => sorted.sort_by_key(|s| (s.frame.y, s.frame.x)),
+            'b' | 'd' => sorted.sort_by_key(|s| (-s.frame.y, s.frame.x)),
+            's' | 'n' | 'p' if n == 0 => {
+                let window = window.ok_or(Error::NeedsWindow(c))?;
+                let current = current_screen(screens, window);
+                if c == 's' {
+                    return Ok(current);
+                }
+                sorted.sort_by_key(|s| (s.frame.x, s.frame.y));
+                let pos = sorted
+                    .iter()
+                    .position(|s| std::ptr::eq(*s, current))
+                    .expect("current is one of the screens.");
+                let next = if c == 'n' {
+                    (pos + 1) % sorted.len()
+                } else {
+                    (pos + sorted.len() - 1) % sorted.len()
+                };
+                return Ok(sorted[next]);
+            }
+            _ => return Err(Error::UnknownSelector(c)),
+        }
+        sorted
+            .get(n)
+            .copied()
+            .ok_or_else(|| Error::NotEnoughScreens {
+                selector: self.to_string(),
+                screens: screens.to_vec(),
+            })
+    }
+}
+
+/// The screen that `window` overlaps the most. Ties go to the screen that comes first.
+fn current_screen<'a>(screens: &'a [Screen], window: &Rect) -> &'a Screen {
+    let mut best = &screens[0];
+    for s in &screens[1..] {
+        if s.frame.overlap_area(window) > best.frame.overlap_area(window) {
+            best = s;
+        }
+    }
+    best
+}
+
+/// What the parser was looking for when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expected {
+    Screen,
+    Digit,
+    Number,
+    Separator,
+    SizeSeparator,
+    Anchor,
+    AreaName,
+    End,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Screen => write!(f, "a screen index or selector character"),
+            Expected::Digit => write!(f, "a digit"),
+            Expected::Number => write!(f, "a number"),
+            Expected::Separator => write!(f, "':'"),
+            Expected::SizeSeparator => write!(f, "'x' or '%'"),
+            Expected::Anchor => write!(f, "an anchor (tl, t, tr, l, c, r, bl, b or br)"),
+            Expected::AreaName => write!(f, "an area name"),
+            Expected::End => write!(f, "end of input"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParseErrorKind {
+    UnexpectedEnd { expected: Expected },
+    UnexpectedChar { found: char, expected: Expected },
+    NumberTooLarge,
+    ZeroRatio,
+    ZeroWeight,
+    ZeroLength,
+    OutsideOfGrid { cell: i32, ratio: i32 },
+    ReversedRange { start: i32, end: i32 },
+}
+
+/// A failure to parse a placement command. `offset` is the byte offset into `input` of the
+/// offending character.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseError {
+    pub input: String,
+    pub offset: usize,
+    #[serde(flatten)]
+    pub kind: ParseErrorKind,
+}
+
+const COMPACT_HINT: &str = "valid commands look like <screen>[<x ratio><x start>[-<x end>][<y ratio><y start>[-<y end>]]], e.g. 031-231";
+const LONG_HINT: &str = "valid commands look like <screen>[:<x parts>[:<x start>[-<x end>][:<y parts>[:<y start>[-<y end>]]]]], where parts are a number or weights like 3,1,1, e.g. 0:12:3-8:2:0 or 0:3,1,1:1-2";
+
+const SIZED_HINT: &str = "valid sized placements look like <screen>:<width>[%]x<height>[%][@<anchor>], e.g. 1:800x600 or 0:40%x70%@tr";
+const AREA_HINT: &str = "valid area placements look like <screen>:<area>, with the areas defined in the config, e.g. 1:term";
+
+impl ParseError {
+    /// A hint of the valid syntax for the form of command that failed to parse.
+    pub fn hint(&self) -> &'static str {
+        if is_area_form(&self.input) {
+            AREA_HINT
+        } else if is_sized_form(&self.input) {
+            SIZED_HINT
+        } else if is_long_form(&self.input) {
+            LONG_HINT
+        } else {
+            COMPACT_HINT
+        }
+    }
+
+    /// Renders the error as the input with a caret under the offending character, followed by a
+    /// hint of the valid syntax.
+    pub fn render(&self) -> String {
+        let column = self.input[..self.offset].chars().count();
+        format!(
+            "error: {}\n  {}\n  {}^\nhint: {}",
+            self,
+            self.input,
+            " ".repeat(column),
+            self.hint()
+        )
+    }
+
+    /// The error as a JSON object, for integrations that want to show it themselves.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut v = serde_json::to_value(self).expect("always serializable.");
+        v["message"] = self.to_string().into();
+        v["hint"] = self.hint().into();
+        v
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedEnd { expected } => {
+                write!(f, "expected {}, found end of input", expected)
+            }
+            ParseErrorKind::UnexpectedChar { found, expected } => {
+                write!(f, "expected {}, found {:?}", expected, found)
+            }
+            ParseErrorKind::NumberTooLarge => write!(f, "number too large"),
+            ParseErrorKind::ZeroRatio => write!(f, "the grid needs at least 1 part"),
+            ParseErrorKind::ZeroWeight => write!(f, "every part needs a weight of at least 1"),
+            ParseErrorKind::ZeroLength => {
+                write!(f, "a window needs to be at least 1 pixel or 1% large")
+            }
+            ParseErrorKind::OutsideOfGrid { cell, ratio } => write!(
+                f,
+                "part {} is outside of a grid with {} parts (counting from 0)",
+                cell, ratio
+            ),
+            ParseErrorKind::ReversedRange { start, end } => {
+                write!(f, "range ends at {} before it starts at {}", end, start)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Commands containing a `:` use the long form, and so do screen aliases without any further
+/// fields: names starting with a letter whose second character is not a digit, e.g. `work`.
+/// Everything else, including mistyped compact commands like `0a1`, uses the compact form.
+fn is_long_form(s: &str) -> bool {
+    let mut chars = s.chars();
+    s.contains(':')
+        || (chars.next().is_some_and(char::is_alphabetic)
+            && chars.next().is_some_and(|c| !c.is_ascii_digit())
+            && s.chars().all(is_area_char))
+}
+
+/// Area placements have a name starting with a letter after the screen.
+fn is_area_form(s: &str) -> bool {
+    s.split_once(':')
+        .is_some_and(|(_, rest)| rest.chars().next().is_some_and(char::is_alphabetic))
+}
+
+/// Sized placements have an `x` or a `%` after the screen.
+fn is_sized_form(s: &str) -> bool {
+    s.split_once(':')
+        .is_some_and(|(_, rest)| rest.contains(['x', '%']))
+}
+
+/// A cursor over the command string that knows its byte offset.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        self.error_at(self.pos, kind)
+    }
+
+    fn error_at(&self, offset: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            input: self.input.to_string(),
+            offset,
+            kind,
+        }
+    }
+
+    /// An error for the character at the cursor, or the end of input.
+    fn unexpected(&self, expected: Expected) -> ParseError {
+        match self.peek() {
+            None => self.error(ParseErrorKind::UnexpectedEnd { expected }),
+            Some(found) => self.error(ParseErrorKind::UnexpectedChar { found, expected }),
+        }
+    }
+
+    fn digit(&mut self) -> Result<i32, ParseError> {
+        match self.peek().and_then(|c| c.to_digit(10)) {
+            Some(v) => {
+                self.pos += 1;
+                Ok(v as i32)
+            }
+            None => Err(self.unexpected(Expected::Digit)),
+        }
+    }
+
+    fn number(&mut self) -> Result<i32, ParseError> {
+        let start = self.pos;
+        let len = self.input[start..]
+            .bytes()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if len == 0 {
+            return Err(self.unexpected(Expected::Number));
+        }
+        match self.input[start..start + len].parse() {
+            Ok(v) => {
+                self.pos += len;
+                Ok(v)
+            }
+            Err(_) => Err(self.error(ParseErrorKind::NumberTooLarge)),
+        }
+    }
+
+    /// Parses the number of parts of a grid using `value`.
+    fn ratio(
+        &mut self,
+        value: fn(&mut Self) -> Result<i32, ParseError>,
+    ) -> Result<i32, ParseError> {
+        let offset = self.pos;
+        match value(self)? {
+            0 => Err(self.error_at(offset, ParseErrorKind::ZeroRatio)),
+            v => Ok(v),
+        }
+    }
+
+    /// Parses the parts of a grid in the long form: either a number of equally large parts or the
+    /// weights of the parts separated by `,`, each optionally followed by `fr`, e.g. `3,1,1` or
+    /// `2fr,1fr`. Returns the number of parts and their weights, which are empty for equal parts.
+    fn parts(&mut self) -> Result<(i32, Vec<i32>), ParseError> {
+        let offset = self.pos;
+        let first = self.number()?;
+        let fr = self.suffix("fr");
+        if !fr && self.peek() != Some(',') {
+            return match first {
+                0 => Err(self.error_at(offset, ParseErrorKind::ZeroRatio)),
+                v => Ok((v, Vec::new())),
+            };
+        }
+        if first == 0 {
+            return Err(self.error_at(offset, ParseErrorKind::ZeroWeight));
+        }
+        let mut weights = vec![first];
+        let mut total = first;
+        while self.peek() == Some(',') {
+            self.next();
+            let offset = self.pos;
+            let weight = self.number()?;
+            self.suffix("fr");
+            if weight == 0 {
+                return Err(self.error_at(offset, ParseErrorKind::ZeroWeight));
+            }
+            total = total
+                .checked_add(weight)
+                .ok_or_else(|| self.error_at(offset, ParseErrorKind::NumberTooLarge))?;
+            weights.push(weight);
+        }
+        Ok((weights.len() as i32, weights))
+    }
+
+    /// Consumes `suffix` if the input continues with it.
+    fn suffix(&mut self, suffix: &str) -> bool {
+        if !self.input[self.pos..].starts_with(suffix) {
+            return false;
+        }
+        self.pos += suffix.len();
+        true
+    }
+
+    /// Parses `start` or `start-end` using `value` for both numbers and checks that the range lies
+    /// inside of a grid with `ratio` parts.
+    fn range(
+        &mut self,
+        value: fn(&mut Self) -> Result<i32, ParseError>,
+        ratio: i32,
+    ) -> Result<(i32, i32), ParseError> {
+        let cell = |p: &mut Self| {
+            let offset = p.pos;
+            match value(p)? {
+                cell if cell >= ratio => {
+                    Err(p.error_at(offset, ParseErrorKind::OutsideOfGrid { cell, ratio }))
+                }
+                cell => Ok((offset, cell)),
+            }
+        };
+        let (_, start) = cell(self)?;
+        if self.peek() != Some('-') {
+            return Ok((start, start));
+        }
+        self.next();
+        match cell(self)? {
+            (offset, end) if end < start => {
+                Err(self.error_at(offset, ParseErrorKind::ReversedRange { start, end }))
+            }
+            (_, end) => Ok((start, end)),
+        }
+    }
+
+    /// The characters from the cursor up to the next `:` or the end of input.
+    fn word(&self) -> std::str::Chars<'a> {
+        let rest = &self.input[self.pos..];
+        rest[..rest.find(':').unwrap_or(rest.len())].chars()
+    }
+
+    /// Parses the screen field of the long form: an index, a selector character optionally
+    /// followed by a number, or an alias.
+    fn screen(&mut self) -> Result<ScreenSelector, ParseError> {
+        Ok(match self.peek() {
+            Some('0'..='9') => ScreenSelector::Index(self.number()? as usize),
+            Some(c) if c != ':' => {
+                let mut word = self.word();
+                word.next();
+                match word.as_str() {
+                    "" => {
+                        self.next();
+                        ScreenSelector::Char(c)
+                    }
+                    rest if rest.bytes().all(|b| b.is_ascii_digit()) => {
+                        self.next();
+                        ScreenSelector::Nth(c, self.number()? as usize)
+                    }
+                    _ => {
+                        let alias = self.word().as_str();
+                        self.pos += alias.len();
+                        ScreenSelector::Alias(alias.to_string())
+                    }
+                }
+            }
+            _ => return Err(self.unexpected(Expected::Screen)),
+        })
+    }
+
+    /// Parses a number of pixels or a percentage, neither of which may be 0.
+    fn length(&mut self) -> Result<Length, ParseError> {
+        let offset = self.pos;
+        let v = self.number()?;
+        if v == 0 {
+            return Err(self.error_at(offset, ParseErrorKind::ZeroLength));
+        }
+        if self.peek() != Some('%') {
+            return Ok(Length::Pixels(v));
+        }
+        self.next();
+        if v > 100 {
+            return Err(self.error_at(offset, ParseErrorKind::NumberTooLarge));
+        }
+        Ok(Length::Percent(v))
+    }
+
+    /// Consumes a `:` or returns false at the end of input.
+    fn separator(&mut self) -> Result<bool, ParseError> {
+        match self.peek() {
+            None => Ok(false),
+            Some(':') => {
+                self.next();
+                Ok(true)
+            }
+            Some(_) => Err(self.unexpected(Expected::Separator)),
+        }
+    }
+}
+
+impl MoveParameters {
+    /// Parses a placement command. Commands containing a `:` or starting with a screen alias use
+    /// the long form `screen:x_ratio:x_range:y_ratio:y_range`, where every field is a (possibly
+    /// multi-digit) number and ranges are `start` or `start-end`, e.g. `0:12:3-8:2:0`. Instead of
+    /// a ratio, the long form takes the weights of parts of different sizes, e.g. `0:3,1,1:0` for
+    /// the first of three columns taking 60%, 20% and 20% of the screen. Everything
+    /// else uses the compact form with one character per field, e.g. `031-231`. In both forms,
+    /// trailing fields can be left out.
+    pub fn from_command(s: &str) -> Result<Self, ParseError> {
+        if is_long_form(s) {
+            Self::from_long_command(s)
+        } else {
+            Self::from_compact_command(s)
+        }
+    }
+
+    fn from_compact_command(s: &str) -> Result<Self, ParseError> {
+        let mut i = Parser::new(s);
+
+        let screen = match i.next() {
+            Some(c @ '0'..='9') => {
+                ScreenSelector::Index(c.to_digit(10).expect("by design.") as usize)
+            }
+            Some(c) => ScreenSelector::Char(c),
+            None => return Err(i.unexpected(Expected::Screen)),
+        };
+
+        let mut params = MoveParameters::new(screen);
+        if i.is_done() {
+            return Ok(params);
+        }
+        params.x_ratio = i.ratio(Parser::digit)?;
+        if i.is_done() {
+            return Ok(params);
+        }
+        (params.x_start, params.x_end) = i.range(Parser::digit, params.x_ratio)?;
+        if i.is_done() {
+            return Ok(params);
+        }
+        params.y_ratio = i.ratio(Parser::digit)?;
+        if i.is_done() {
+            return Ok(params);
+        }
+        (params.y_start, params.y_end) = i.range(Parser::digit, params.y_ratio)?;
+        if !i.is_done() {
+            return Err(i.unexpected(Expected::End));
+        }
+        Ok(params)
+    }
+
+    fn from_long_command(s: &str) -> Result<Self, ParseError> {
+        let mut i = Parser::new(s);
+
+        let screen = i.screen()?;
+
+        let mut params = MoveParameters::new(screen);
+        if !i.separator()? {
+            return Ok(params);
+        }
+        (params.x_ratio, params.x_weights) = i.parts()?;
+        if !i.separator()? {
+            return Ok(params);
+        }
+        (params.x_start, params.x_end) = i.range(Parser::number, params.x_ratio)?;
+        if !i.separator()? {
+            return Ok(params);
+        }
+        (params.y_ratio, params.y_weights) = i.parts()?;
+        if !i.separator()? {
+            return Ok(params);
+        }
+        (params.y_start, params.y_end) = i.range(Parser::number, params.y_ratio)?;
+        if !i.is_done() {
+            return Err(i.unexpected(Expected::End));
+        }
+        Ok(params)
+    }
+
+    /// Formats the parameters in the compact form, leaving out trailing fields that cover the
+    /// whole screen, e.g. `021` for the right half. Returns `None` if the compact form cannot
+    /// express them.
+    pub fn to_compact(&self) -> Option<String> {
+        let single = |v: i32| (0..10).contains(&v).then(|| v.to_string());
+        let range = |start: i32, end: i32| {
+            Some(if start == end {
+                single(start)?
+            } else {
+                format!("{}-{}", single(start)?, single(end)?)
+            })
+        };
+        let mut rv = match self.screen {
+            ScreenSelector::Index(index) if index < 10 => index.to_string(),
+            ScreenSelector::Char(c) if !c.is_ascii_digit() => c.to_string(),
+            _ => return None,
+        };
+        if !self.x_weights.is_empty() || !self.y_weights.is_empty() {
+            return None;
+        }
+        if self.x_ratio == 1 && self.y_ratio == 1 {
+            return Some(rv);
+        }
+        rv.push_str(&single(self.x_ratio)?);
+        rv.push_str(&range(self.x_start, self.x_end)?);
+        if self.y_ratio == 1 {
+            return Some(rv);
+        }
+        rv.push_str(&single(self.y_ratio)?);
+        rv.push_str(&range(self.y_start, self.y_end)?);
+        Some(rv)
+    }
+
+    /// The whole of the screen selected by `screen`.
+    fn new(screen: ScreenSelector) -> Self {
+        MoveParameters {
+            screen,
+            x_ratio: 1,
+            y_ratio: 1,
+            x_weights: Vec::new(),
+            y_weights: Vec::new(),
+            x_start: 0,
+            x_end: 0,
+            y_start: 0,
+            y_end: 0,
+        }
+    }
+}
+
+/// Formats the parameters in the long form, which `from_command` parses back into the same value.
+impl fmt::Display for MoveParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = |start: i32, end: i32| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        };
+        let parts = |ratio: i32, weights: &[i32]| {
+            if weights.is_empty() {
+                ratio.to_string()
+            } else {
+                let weights = weights.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+                weights.join(",")
+            }
+        };
+        write!(
+            f,
+            "{}:{}:{}:{}:{}",
+            self.screen,
+            parts(self.x_ratio, &self.x_weights),
+            range(self.x_start, self.x_end),
+            parts(self.y_ratio, &self.y_weights),
+            range(self.y_start, self.y_end)
+        )
+    }
+}
+
+/// Formats the placement so that `Placement::from_command` parses it back into the same value.
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Placement::Grid(params) => write!(f, "{}", params),
+            Placement::Sized(sized) => write!(f, "{}", sized),
+            Placement::Area(area) => write!(f, "{}", area),
+        }
+    }
+}
+
+impl fmt::Display for SizedPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let anchor = Anchor::ALL
+            .iter()
+            .find(|(_, a)| *a == self.anchor)
+            .expect("all anchors are listed.")
+            .0;
+        write!(
+            f,
+            "{}:{}x{}@{}",
+            self.screen, self.width, self.height, anchor
+        )
+    }
+}
+
+impl fmt::Display for AreaPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.screen, self.name)
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Pixels(p) => write!(f, "{}", p),
+            Length::Percent(p) => write!(f, "{}%", p),
+        }
+    }
+}
+
+impl fmt::Display for ScreenSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenSelector::Index(i) => write!(f, "{}", i),
+            ScreenSelector::Char(c) => write!(f, "{}", c),
+            ScreenSelector::Nth(c, n) => write!(f, "{}{}", c, n),
+            ScreenSelector::Alias(a) => write!(f, "{}", a),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(
+        screen: ScreenSelector,
+        (x_ratio, x_start, x_end): (i32, i32, i32),
+        (y_ratio, y_start, y_end): (i32, i32, i32),
+    ) -> MoveParameters {
+        MoveParameters {
+            screen,
+            x_ratio,
+            y_ratio,
+            x_weights: Vec::new(),
+            y_weights: Vec::new(),
+            x_start,
+            x_end,
+            y_start,
+            y_end,
+        }
+    }
+
+    #[test]
+    fn parses_compact_commands() {
+        let index = ScreenSelector::Index;
+        for (command, expected) in [
+            ("0", params(index(0), (1, 0, 0), (1, 0, 0))),
+            ("021", params(index(0), (2, 1, 1), (1, 0, 0))),
+            ("02031", params(index(0), (2, 0, 0), (3, 1, 1))),
+            ("031-231", params(index(0), (3, 1, 2), (3, 1, 1))),
+            (
+                "m21",
+                params(ScreenSelector::Char('m'), (2, 1, 1), (1, 0, 0)),
+            ),
+        ] {
+            assert_eq!(
+                MoveParameters::from_command(command),
+                Ok(expected),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn parses_long_commands() {
+        for (command, expected) in [
+            (
+                "0:12:3-8:2:0",
+                params(ScreenSelector::Index(0), (12, 3, 8), (2, 0, 0)),
+            ),
+            (
+                "12:10:9",
+                params(ScreenSelector::Index(12), (10, 9, 9), (1, 0, 0)),
+            ),
+            (
+                "l1:2:0",
+                params(ScreenSelector::Nth('l', 1), (2, 0, 0), (1, 0, 0)),
+            ),
+            (
+                "work",
+                params(
+                    ScreenSelector::Alias("work".to_string()),
+                    (1, 0, 0),
+                    (1, 0, 0),
+                ),
+            ),
+        ] {
+            assert_eq!(
+                MoveParameters::from_command(command),
+                Ok(expected),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn display_round_trips_for_every_screen_selector() {
+        let screens = [
+            ScreenSelector::Index(0),
+            ScreenSelector::Index(13),
+            ScreenSelector::Char('m'),
+            ScreenSelector::Char('s'),
+            ScreenSelector::Nth('r', 2),
+            ScreenSelector::Alias("work".to_string()),
+        ];
+        let grids = [
+            ((1, 0, 0), (1, 0, 0)),
+            ((2, 1, 1), (1, 0, 0)),
+            ((3, 1, 2), (3, 1, 1)),
+            ((12, 3, 8), (2, 0, 0)),
+            ((100, 0, 99), (24, 23, 23)),
+        ];
+        for screen in screens {
+            for (x, y) in grids {
+                let params = params(screen.clone(), x, y);
+                let long = params.to_string();
+                assert_eq!(
+                    MoveParameters::from_command(&long),
+                    Ok(params.clone()),
+                    "{}",
+                    long
+                );
+                if let Some(compact) = params.to_compact() {
+                    assert_eq!(
+                        MoveParameters::from_command(&compact),
+                        Ok(params),
+                        "{}",
+                        compact
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compact_form_leaves_out_trailing_fields() {
+        let index = ScreenSelector::Index;
+        assert_eq!(
+            params(index(0), (1, 0, 0), (1, 0, 0))
+                .to_compact()
+                .as_deref(),
+            Some("0")
+        );
+        assert_eq!(
+            params(index(0), (2, 1, 1), (1, 0, 0))
+                .to_compact()
+                .as_deref(),
+            Some("021")
+        );
+        assert_eq!(
+            params(index(0), (3, 1, 2), (3, 1, 1))
+                .to_compact()
+                .as_deref(),
+            Some("031-231")
+        );
+        assert_eq!(params(index(0), (12, 3, 8), (2, 0, 0)).to_compact(), None);
+        assert_eq!(
+            params(ScreenSelector::Nth('l', 1), (2, 0, 0), (1, 0, 0)).to_compact(),
+            None
+        );
+    }
+
+    fn parse_error(command: &str) -> ParseError {
+        MoveParameters::from_command(command).unwrap_err()
+    }
+
+    #[test]
+    fn mistyped_compact_commands_get_the_compact_hint() {
+        let err = parse_error("0a1");
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::UnexpectedChar {
+                found: 'a',
+                expected: Expected::Digit
+            }
+        );
+        assert_eq!(err.hint(), COMPACT_HINT);
+        assert_eq!(parse_error("031-2x1").hint(), COMPACT_HINT);
+        assert_eq!(parse_error("0:12:-").hint(), LONG_HINT);
+    }
+
+    #[test]
+    fn aliases_and_colons_use_the_long_form() {
+        assert!(is_long_form("work"));
+        assert!(is_long_form("left-monitor"));
+        assert!(is_long_form("0:12:3"));
+        assert!(!is_long_form("0a1"));
+        assert!(!is_long_form("m21"));
+        assert!(!is_long_form("m"));
+    }
+
+    #[test]
+    fn render_points_at_the_offending_character() {
+        assert_eq!(
+            parse_error("0a1").render(),
+            format!(
+                "error: expected a digit, found 'a'\n  0a1\n   ^\nhint: {}",
+                COMPACT_HINT
+            )
+        );
+        assert_eq!(
+            parse_error("0:12:3-y").render(),
+            format!(
+                "error: expected a number, found 'y'\n  0:12:3-y\n         ^\nhint: {}",
+                LONG_HINT
+            )
+        );
+        // The caret counts characters, not bytes.
+        assert_eq!(parse_error("ü:1x").render().lines().nth(2), Some("     ^"));
+    }
+
+    #[test]
+    fn to_json_has_the_kind_offset_message_and_hint() {
+        assert_eq!(
+            parse_error("02x").to_json(),
+            serde_json::json!({
+                "input": "02x",
+                "offset": 2,
+                "kind": "unexpected_char",
+                "found": "x",
+                "expected": "digit",
+                "message": "expected a digit, found 'x'",
+                "hint": COMPACT_HINT,
+            })
+        );
+        assert_eq!(
+            parse_error("0:").to_json(),
+            serde_json::json!({
+                "input": "0:",
+                "offset": 2,
+                "kind": "unexpected_end",
+                "expected": "number",
+                "message": "expected a number, found end of input",
+                "hint": LONG_HINT,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_grids_without_parts_and_ranges_outside_or_reversed() {
+        for (command, offset, kind) in [
+            ("00", 1, ParseErrorKind::ZeroRatio),
+            ("0:0", 2, ParseErrorKind::ZeroRatio),
+            ("0:2:0:0", 6, ParseErrorKind::ZeroRatio),
+            (
+                "023",
+                2,
+                ParseErrorKind::OutsideOfGrid { cell: 3, ratio: 2 },
+            ),
+            (
+                "0:12:3-13",
+                7,
+                ParseErrorKind::OutsideOfGrid {
+                    cell: 13,
+                    ratio: 12,
+                },
+            ),
+            (
+                "032-1",
+                4,
+                ParseErrorKind::ReversedRange { start: 2, end: 1 },
+            ),
+            ("0:99999999999", 2, ParseErrorKind::NumberTooLarge),
+        ] {
+            let err = parse_error(command);
+            assert_eq!((err.offset, err.kind), (offset, kind), "{}", command);
+        }
+        assert_eq!(
+            parse_error("0:12:3-13").render(),
+            format!(
+                "error: part 13 is outside of a grid with 12 parts (counting from 0)\n  \
+                 0:12:3-13\n         ^\nhint: {}",
+                LONG_HINT
+            )
+        );
+    }
+
+    #[test]
+    fn parses_weights() {
+        let weighted = |command: &str| {
+            let params = MoveParameters::from_command(command).unwrap();
+            (
+                params.x_ratio,
+                params.x_weights,
+                params.x_start,
+                params.x_end,
+                params.y_ratio,
+                params.y_weights,
+            )
+        };
+        assert_eq!(weighted("0:3,1,1:1-2"), (3, vec![3, 1, 1], 1, 2, 1, vec![]));
+        assert_eq!(weighted("0:2fr,1fr:1"), (2, vec![2, 1], 1, 1, 1, vec![]));
+        assert_eq!(weighted("0:2fr"), (1, vec![2], 0, 0, 1, vec![]));
+        assert_eq!(weighted("0:2:0:1,2fr:1"), (2, vec![], 0, 0, 2, vec![1, 2]));
+        for command in ["0:3,1,1:1-2", "0:2,1:1:1,2:0"] {
+            let params = MoveParameters::from_command(command).unwrap();
+            assert_eq!(
+                MoveParameters::from_command(&params.to_string()),
+                Ok(params.clone())
+            );
+            assert_eq!(params.to_compact(), None);
+        }
+    }
+
+    #[test]
+    fn rejects_zero_and_overflowing_weights() {
+        for (command, offset, kind) in [
+            ("0:0,1", 2, ParseErrorKind::ZeroWeight),
+            ("0:0fr", 2, ParseErrorKind::ZeroWeight),
+            ("0:3,0,1", 4, ParseErrorKind::ZeroWeight),
+            ("0:1:0:2fr,0fr", 10, ParseErrorKind::ZeroWeight),
+            ("0:2147483647,1", 13, ParseErrorKind::NumberTooLarge),
+            ("0:1,2147483648", 4, ParseErrorKind::NumberTooLarge),
+            (
+                "0:2,1:2",
+                6,
+                ParseErrorKind::OutsideOfGrid { cell: 2, ratio: 2 },
+            ),
+        ] {
+            let err = parse_error(command);
+            assert_eq!((err.offset, err.kind), (offset, kind), "{}", command);
+        }
+    }
+
+    /// Four screens: the main one, a smaller one to its left at negative x, one stacked on top of
+    /// it and a bigger one to its right.
+    fn layout() -> Vec<Screen> {
+        let screen = |index, id: &str, name: &str, x, y, width, height| Screen {
+            index,
+            id: id.to_string(),
+            name: name.to_string(),
+            visible_frame: rect(x, y, width, height),
+            frame: rect(x, y, width, height),
+            scale_factor: 1.,
+        };
+        vec![
+            screen(0, "uuid-main", "Built-in", 0, 0, 1920, 1080),
+            screen(1, "uuid-left", "Left", -1280, 200, 1280, 800),
+            screen(2, "uuid-top", "Top", 0, -1080, 1920, 1080),
+            screen(3, "uuid-right", "Right", 1920, 0, 2560, 1440),
+        ]
+    }
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn selects_screens_by_index_side_and_window() {
+        let screens = layout();
+        let on_main = rect(100, 100, 800, 600);
+        let mostly_right = rect(1800, 100, 400, 300);
+        let evenly_split = rect(1820, 100, 200, 300);
+        let on_left = rect(-1000, 300, 400, 300);
+        let on_top = rect(100, -900, 400, 300);
+        let char = ScreenSelector::Char;
+        let nth = ScreenSelector::Nth;
+        for (selector, window, expected) in [
+            (ScreenSelector::Index(2), None, 2),
+            (char('m'), None, 0),
+            (char('c'), None, 0),
+            // Screens sharing an x are sorted top to bottom, sharing a y left to right.
+            (char('l'), None, 1),
+            (nth('l', 1), None, 2),
+            (nth('l', 2), None, 0),
+            (nth('l', 3), None, 3),
+            (char('r'), None, 3),
+            (nth('r', 1), None, 2),
+            (nth('r', 2), None, 0),
+            (nth('r', 3), None, 1),
+            (char('t'), None, 2),
+            (nth('u', 1), None, 0),
+            (nth('t', 2), None, 3),
+            (char('b'), None, 1),
+            (nth('d', 1), None, 0),
+            (nth('b', 2), None, 3),
+            (nth('b', 3), None, 2),
+            // The window's screen is the one it overlaps the most, the first one on a tie.
+            (char('s'), Some(&on_main), 0),
+            (char('s'), Some(&mostly_right), 3),
+            (char('s'), Some(&evenly_split), 0),
+            // Next and previous go left to right and wrap around.
+            (char('n'), Some(&on_main), 3),
+            (char('n'), Some(&mostly_right), 1),
+            (char('n'), Some(&on_left), 2),
+            (char('p'), Some(&on_top), 1),
+            (char('p'), Some(&on_left), 3),
+            (char('p'), Some(&on_main), 2),
+        ] {
+            let selected = selector.select(&screens, window, &HashMap::new());
+            assert_eq!(
+                selected.map(|s| s.index).ok(),
+                Some(expected),
+                "{} with {:?}",
+                selector,
+                window
+            );
+        }
+    }
+
+    #[test]
+    fn selecting_missing_screens_fails() {
+        let screens = layout();
+        let no_aliases = HashMap::new();
+        let select = |selector: ScreenSelector| selector.select(&screens, None, &no_aliases);
+        assert!(matches!(
+            select(ScreenSelector::Index(4)),
+            Err(Error::UnknownScreen { index: 4, .. })
+        ));
+        assert!(matches!(
+            select(ScreenSelector::Nth('l', 4)),
+            Err(Error::NotEnoughScreens { selector, .. }) if selector == "l4"
+        ));
+        assert!(matches!(
+            select(ScreenSelector::Char('x')),
+            Err(Error::UnknownSelector('x'))
+        ));
+        assert!(matches!(
+            select(ScreenSelector::Char('n')),
+            Err(Error::NeedsWindow('n'))
+        ));
+        assert!(matches!(
+            ScreenSelector::Char('l').select(&[], None, &no_aliases),
+            Err(Error::NoScreens)
+        ));
+    }
+
+    #[test]
+    fn selects_aliased_screens_by_id_or_name() {
+        let mut screens = layout();
+        // An id that is also another screen's name: ids win.
+        screens[3].name = "uuid-left".to_string();
+        let aliases = [
+            ("laptop", "uuid-main"),
+            ("side", "Left"),
+            ("either", "uuid-left"),
+            ("gone", "uuid-projector"),
+        ]
+        .into_iter()
+        .map(|(alias, target)| (alias.to_string(), target.to_string()))
+        .collect::<HashMap<_, _>>();
+        let select =
+            |alias: &str| ScreenSelector::Alias(alias.to_string()).select(&screens, None, &aliases);
+        assert_eq!(select("laptop").map(|s| s.index).ok(), Some(0));
+        assert_eq!(select("side").map(|s| s.index).ok(), Some(1));
+        assert_eq!(select("either").map(|s| s.index).ok(), Some(1));
+        assert!(matches!(
+            select("gone"),
+            Err(Error::AliasNotConnected { alias, target, .. })
+                if alias == "gone" && target == "uuid-projector"
+        ));
+        assert!(matches!(
+            select("work"),
+            Err(Error::UnknownAlias { alias, aliases }) if alias == "work" && aliases.len() == 4
+        ));
+    }
+
+    #[test]
+    fn parses_sized_placements() {
+        let sized = |screen, width, height, anchor| SizedPlacement {
+            screen,
+            width,
+            height,
+            anchor,
+        };
+        for (command, expected) in [
+            (
+                "1:800x600",
+                sized(
+                    ScreenSelector::Index(1),
+                    Length::Pixels(800),
+                    Length::Pixels(600),
+                    Anchor::Center,
+                ),
+            ),
+            (
+                "0:40%x70%@tr",
+                sized(
+                    ScreenSelector::Index(0),
+                    Length::Percent(40),
+                    Length::Percent(70),
+                    Anchor::TopRight,
+                ),
+            ),
+            (
+                "s:100%x400@b",
+                sized(
+                    ScreenSelector::Char('s'),
+                    Length::Percent(100),
+                    Length::Pixels(400),
+                    Anchor::Bottom,
+                ),
+            ),
+            (
+                "l1:1x1@bl",
+                sized(
+                    ScreenSelector::Nth('l', 1),
+                    Length::Pixels(1),
+                    Length::Pixels(1),
+                    Anchor::BottomLeft,
+                ),
+            ),
+        ] {
+            assert_eq!(
+                SizedPlacement::from_command(command),
+                Ok(expected.clone()),
+                "{}",
+                command
+            );
+            assert_eq!(
+                Placement::from_command(&expected.to_string()),
+                Ok(Placement::Sized(expected)),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_empty_oversized_and_malformed_sizes() {
+        let too_large = ParseErrorKind::NumberTooLarge;
+        for (command, offset, kind) in [
+            ("0:0x0", 2, ParseErrorKind::ZeroLength),
+            ("0:0%x50%", 2, ParseErrorKind::ZeroLength),
+            ("0:50%x0", 6, ParseErrorKind::ZeroLength),
+            ("0:101%x50%", 2, too_large.clone()),
+            ("0:800x2147483648", 6, too_large),
+            (
+                "0:800x600@middle",
+                10,
+                ParseErrorKind::UnexpectedChar {
+                    found: 'm',
+                    expected: Expected::Anchor,
+                },
+            ),
+            (
+                "0:800x600!",
+                9,
+                ParseErrorKind::UnexpectedChar {
+                    found: '!',
+                    expected: Expected::End,
+                },
+            ),
+        ] {
+            let err = Placement::from_command(command).unwrap_err();
+            assert_eq!(err.hint(), SIZED_HINT, "{}", command);
+            assert_eq!((err.offset, err.kind), (offset, kind), "{}", command);
+        }
+    }
+
+    #[test]
+    fn anchors_align_along_both_axes() {
+        for (anchor, alignment) in [
+            (Anchor::TopLeft, (0, 0)),
+            (Anchor::Top, (1, 0)),
+            (Anchor::TopRight, (2, 0)),
+            (Anchor::Left, (0, 1)),
+            (Anchor::Center, (1, 1)),
+            (Anchor::Right, (2, 1)),
+            (Anchor::BottomLeft, (0, 2)),
+            (Anchor::Bottom, (1, 2)),
+            (Anchor::BottomRight, (2, 2)),
+        ] {
+            assert_eq!(anchor.alignment(), alignment, "{:?}", anchor);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::Rect;
+use crate::backend::Screen;
+use crate::command::Placement;
+use crate::geometry::Insets;
+use crate::template::Template;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+/// User configuration, read from `config.json` in the config directory, see `config_dir`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// pixels between adjacent tiles
+    pub gap: i32,
+    /// pixels between tiles and the edges of the screen
+    pub margin: i32,
+    /// mapping from screen (see `for_screen`) to space reserved at its edges, e.g. for a custom
+    /// status bar
+    pub insets: HashMap<String, Insets>,
+    /// mapping from a user defined screen name to the stable id or the name of a screen
+    pub aliases: HashMap<String, String>,
+    /// lists of placement commands. Repeating a command of a cycle on a window that already sits
+    /// in one of the cycle's frames moves it to the next frame of the cycle.
+    pub cycles: Vec<Vec<String>>,
+    /// `cycles`, parsed by `validate`
+    #[serde(skip)]
+    parsed_cycles: Vec<Vec<Placement>>,
+    /// how many pixels each edge of a window may be off to still count as sitting in a frame
+    pub tolerance: i32,
+    /// mapping from screen (see `for_screen`) to the rows of a template with named areas, see
+    /// `Template`
+    pub templates: HashMap<String, Vec<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            gap: 0,
+            margin: 0,
+            insets: HashMap::new(),
+            aliases: HashMap::new(),
+            cycles: Vec::new(),
+            parsed_cycles: Vec::new(),
+            tolerance: 10,
+            templates: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from the JSON file, or returns the default config if there is none.
+    pub fn load() -> Result<Self> {
+        let config_file = crate::config_dir()?.join("config.json");
+        if !config_file.exists() {
+            return Ok(Config::default());
+        }
+        let data = fs::read_to_string(&config_file).context("Failed to read config file")?;
+        let mut config: Config =
+            serde_json::from_str(&data).context("Failed to parse config file as JSON")?;
+        config.validate().context("Invalid config file")?;
+        Ok(config)
+    }
+
+    /// Rejects negative spacing, which would make tiles grow out of their screen, and parses the
+    /// cycles, rejecting invalid commands.
+    fn validate(&mut self) -> Result<()> {
+        if self.gap < 0 {
+            bail!("gap must not be negative, but is {}.", self.gap);
+        }
+        if self.margin < 0 {
+            bail!("margin must not be negative, but is {}.", self.margin);
+        }
+        for (screen, insets) in &self.insets {
+            let Insets {
+                top,
+                right,
+                bottom,
+                left,
+            } = *insets;
+            if top < 0 || right < 0 || bottom < 0 || left < 0 {
+                bail!("The insets of screen {} must not be negative.", screen);
+            }
+        }
+        self.parsed_cycles = self
+            .cycles
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .map(|c| {
+                        Placement::from_command(c)
+                            .with_context(|| format!("Invalid command in cycle: {}", c))
+                    })
+                    .collect()
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// The part of `screen` that tiles are placed in: its visible frame minus the reserved insets
+    /// and the outer margin.
+    pub fn tiling_area(&self, screen: &Screen) -> Rect {
+        let insets = self
+            .for_screen(&self.insets, screen)
+            .copied()
+            .unwrap_or_default();
+        screen
+            .visible_frame
+            .inset(&insets)
+            .inset(&Insets::uniform(self.margin))
+    }
+
+    /// Returns the template of `screen`, parsed, if the config defines one.
+    pub fn template(&self, screen: &Screen) -> Result<Option<Template>> {
+        self.for_screen(&self.templates, screen)
+            .map(|rows| {
+                Template::parse(rows)
+                    .with_context(|| format!("Invalid template for screen {}", screen.index))
+            })
+            .transpose()
+    }
+
+    /// Returns the value of `map` for `screen`. Keys are screen aliases, stable ids or names, which
+    /// stay with a display when displays are reconnected, or screen indices, which do not. If
+    /// several aliases of the screen are keys, the alphabetically first one wins.
+    fn for_screen<'a, T>(&self, map: &'a HashMap<String, T>, screen: &Screen) -> Option<&'a T> {
+        map.iter()
+            .filter(|(key, _)| {
+                self.aliases
+                    .get(*key)
+                    .is_some_and(|target| *target == screen.id || *target == screen.name)
+            })
+            .min_by_key(|(key, _)| *key)
+            .map(|(_, value)| value)
+            .or_else(|| map.get(&screen.id))
+            .or_else(|| map.get(&screen.name))
+            .or_else(|| map.get(&screen.index.to_string()))
+    }
+
+    /// Returns the first cycle that contains `placement`.
+    pub fn cycle_containing(&self, placement: &Placement) -> Option<&[Placement]> {
+        self.parsed_cycles
+            .iter()
+            .find(|steps| steps.contains(placement))
+            .map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::ScreenSelector;
+
+    fn config(json: &str) -> Config {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn screen() -> Screen {
+        let frame = Rect {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        };
+        Screen {
+            index: 0,
+            id: "uuid".to_string(),
+            name: "Built-in".to_string(),
+            visible_frame: frame.clone(),
+            frame,
+            scale_factor: 1.,
+        }
+    }
+
+    fn top_inset(config: &Config, screen: &Screen) -> i32 {
+        config.tiling_area(screen).y
+    }
+
+    #[test]
+    fn negative_spacing_is_rejected() {
+        assert!(config(r#"{"gap": 10, "margin": 5}"#).validate().is_ok());
+        assert!(config(r#"{"gap": -1}"#).validate().is_err());
+        assert!(config(r#"{"margin": -1}"#).validate().is_err());
+        assert!(
+            config(r#"{"insets": {"0": {"top": -25}}}"#)
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn huge_margins_leave_a_pixel() {
+        let area = config(r#"{"margin": 2000}"#).tiling_area(&screen());
+        assert_eq!((area.width, area.height), (1, 1));
+    }
+
+    #[test]
+    fn screens_are_found_by_alias_id_name_or_index() {
+        let screen = screen();
+        for key in ["laptop", "uuid", "Built-in", "0"] {
+            let config = config(&format!(
+                r#"{{"aliases": {{"laptop": "uuid"}}, "insets": {{"{}": {{"top": 30}}}}}}"#,
+                key
+            ));
+            assert_eq!(top_inset(&config, &screen), 30, "{}", key);
+        }
+        let config = config(r#"{"insets": {"1": {"top": 30}, "other": {"top": 40}}}"#);
+        assert_eq!(top_inset(&config, &screen), 0);
+    }
+
+    #[test]
+    fn stable_keys_win_over_the_index() {
+        let config = config(
+            r#"{"aliases": {"laptop": "Built-in"},
+                "templates": {"0": ["a b"], "laptop": ["a", "b"]}}"#,
+        );
+        let template = config.template(&screen()).unwrap().unwrap();
+        assert_eq!(
+            template
+                .area(ScreenSelector::Index(0), "b")
+                .unwrap()
+                .y_ratio,
+            2
+        );
+    }
+
+    #[test]
+    fn cycles_are_parsed_when_validated() {
+        let mut cycles = config(r#"{"cycles": [["021", "0:3:2"], ["020", "0:3:0"]]}"#);
+        cycles.validate().unwrap();
+        let placement = |c| Placement::from_command(c).unwrap();
+        let cycle = cycles.cycle_containing(&placement("0:3:0")).unwrap();
+        assert_eq!(cycle, [placement("020"), placement("0:3:0")]);
+        assert!(cycles.cycle_containing(&placement("0:3:1")).is_none());
+
+        let err = config(r#"{"cycles": [["021", "0:3:x"]]}"#)
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid command in cycle: 0:3:x");
+    }
+
+    #[test]
+    fn the_first_of_several_aliases_wins() {
+        let screen = screen();
+        // Every map iterates in a different order, so parse the config a few times.
+        for _ in 0..10 {
+            let config = config(
+                r#"{"aliases": {"laptop": "uuid", "builtin": "Built-in", "main": "uuid"},
+                    "insets": {"main": {"top": 10}, "laptop": {"top": 20}, "builtin": {"top": 30}}}"#,
+            );
+            assert_eq!(top_inset(&config, &screen), 30);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+// Only the macOS backend needs these conversions, but they are pure, so they are built everywhere.
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+use crate::Rect;
+use crate::backend::Screen;
+
+/// Converts `rect` from Cocoa's global coordinates, which have their origin at the bottom left of
+/// the primary screen with y going up, into Quartz global coordinates, which have their origin at
+/// the top left of the primary screen with y going down. `primary_height` is the height of the
+/// primary screen's frame. This works for screens in any arrangement, including ones above, below
+/// or left of the primary screen.
+pub fn cocoa_to_quartz(rect: &Rect, primary_height: i32) -> Rect {
+    Rect {
+        x: rect.x,
+        y: primary_height - rect.y - rect.height,
+        width: rect.width,
+        height: rect.height,
+    }
+}
+
+/// Converts the frames of all `screens` from Cocoa into Quartz coordinates. Like in
+/// `NSScreen::screens`, the first screen must be the primary one, i.e. the one with the menu bar.
+pub fn screens_to_quartz(screens: &mut [Screen]) {
+    let Some(primary) = screens.first() else {
+        return;
+    };
+    let primary_height = primary.frame.height;
+    for screen in screens {
+        screen.frame = cocoa_to_quartz(&screen.frame, primary_height);
+        screen.visible_frame = cocoa_to_quartz(&screen.visible_frame, primary_height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn screen(index: u64, frame: Rect, visible_frame: Rect) -> Screen {
+        Screen {
+            index,
+            id: String::new(),
+            name: String::new(),
+            visible_frame,
+            frame,
+            scale_factor: 1.,
+        }
+    }
+
+    /// A 1920x1080 primary screen with a 25 pixel menu bar and a second screen at `frame` (in
+    /// Cocoa coordinates), and where that second screen must end up in Quartz coordinates.
+    const ARRANGEMENTS: &[(&str, Rect, Rect)] = &[
+        (
+            "above",
+            rect(0, 1080, 2560, 1440),
+            rect(0, -1440, 2560, 1440),
+        ),
+        (
+            "below",
+            rect(0, -1200, 1920, 1200),
+            rect(0, 1080, 1920, 1200),
+        ),
+        (
+            "left, bottom aligned",
+            rect(-1280, 0, 1280, 1024),
+            rect(-1280, 56, 1280, 1024),
+        ),
+        (
+            "left, top aligned",
+            rect(-1280, 56, 1280, 1024),
+            rect(-1280, 0, 1280, 1024),
+        ),
+        (
+            "right, smaller and raised",
+            rect(1920, 200, 1280, 800),
+            rect(1920, 80, 1280, 800),
+        ),
+        (
+            "right, taller",
+            rect(1920, -360, 1440, 2560),
+            rect(1920, -1120, 1440, 2560),
+        ),
+        (
+            "below left",
+            rect(-2560, -1440, 2560, 1440),
+            rect(-2560, 1080, 2560, 1440),
+        ),
+    ];
+
+    #[test]
+    fn converts_screens_in_any_arrangement() {
+        for (name, cocoa, quartz) in ARRANGEMENTS {
+            let mut screens = vec![
+                screen(0, rect(0, 0, 1920, 1080), rect(0, 0, 1920, 1055)),
+                screen(1, cocoa.clone(), cocoa.clone()),
+            ];
+            screens_to_quartz(&mut screens);
+            assert_eq!(screens[0].frame, rect(0, 0, 1920, 1080), "{}", name);
+            assert_eq!(
+                screens[0].visible_frame,
+                rect(0, 25, 1920, 1055),
+                "{}",
+                name
+            );
+            assert_eq!(screens[1].frame, *quartz, "{}", name);
+            assert_eq!(screens[1].visible_frame, *quartz, "{}", name);
+        }
+    }
+
+    #[test]
+    fn conversion_is_its_own_inverse() {
+        for (name, cocoa, quartz) in ARRANGEMENTS {
+            assert_eq!(cocoa_to_quartz(quartz, 1080), *cocoa, "{}", name);
+        }
+    }
+
+    #[test]
+    fn no_screens_is_fine() {
+        screens_to_quartz(&mut []);
+    }
+}
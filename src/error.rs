@@ -0,0 +1,159 @@
+use crate::backend::Screen;
+use crate::command::ParseError;
+use std::fmt;
+
+/// Errors that scripts calling us might want to tell apart. Each has its own exit code, everything
+/// else exits with 1.
+#[derive(Debug)]
+pub enum Error {
+    /// The command could not be parsed.
+    Parse(ParseError),
+    /// The screen selector character is not known.
+    UnknownSelector(char),
+    /// There is no screen with the requested index.
+    UnknownScreen { index: usize, screens: Vec<Screen> },
+    /// A directional selector asked for more screens than there are.
+    NotEnoughScreens {
+        selector: String,
+        screens: Vec<Screen>,
+    },
+    /// The screen alias is not defined in the config.
+    UnknownAlias { alias: String, aliases: Vec<String> },
+    /// No connected screen has the id or name that the alias maps to.
+    AliasNotConnected {
+        alias: String,
+        target: String,
+        screens: Vec<Screen>,
+    },
+    /// The screen's template has no area with this name, or there is no template at all.
+    UnknownArea {
+        area: String,
+        screen: u64,
+        areas: Vec<String>,
+    },
+    /// The selector is relative to the window's screen, but there is no window.
+    NeedsWindow(char),
+    /// The backend reported no screens at all.
+    NoScreens,
+    /// No open window matches the window selector.
+    NoMatchingWindow(String),
+    /// We are not allowed to control other applications.
+    #[cfg(target_os = "macos")]
+    AccessibilityNotGranted,
+}
+
+/// The selector characters and what they select.
+pub const SELECTOR_HELP: &str = "m or c (main), l (left), r (right), t or u (top), b or d (bottom), \
+s (the window's screen), n (next screen to the right), p (previous screen to the left)";
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Parse(_) => 2,
+            Error::UnknownSelector(_) => 3,
+            Error::UnknownScreen { .. } => 4,
+            Error::NotEnoughScreens { .. } => 4,
+            Error::UnknownAlias { .. } => 3,
+            Error::AliasNotConnected { .. } => 4,
+            Error::UnknownArea { .. } => 3,
+            Error::NoScreens => 5,
+            Error::NeedsWindow(_) => 7,
+            Error::NoMatchingWindow(_) => 8,
+            #[cfg(target_os = "macos")]
+            Error::AccessibilityNotGranted => 6,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{}", e.render()),
+            Error::UnknownSelector(c) => write!(
+                f,
+                "Unknown character for screen selection: {:?}. Use a screen index or one of {}.",
+                c, SELECTOR_HELP
+            ),
+            Error::UnknownScreen { index, screens } => {
+                write!(f, "Unknown screen index {}.", index)?;
+                write_screens(f, screens)
+            }
+            Error::NotEnoughScreens { selector, screens } => {
+                write!(f, "There is no screen {}.", selector)?;
+                write_screens(f, screens)
+            }
+            Error::UnknownAlias { alias, aliases } => {
+                let mut aliases = aliases.clone();
+                aliases.sort();
+                write!(
+                    f,
+                    "Unknown screen alias {:?}. Defined aliases: {}.",
+                    alias,
+                    aliases.join(", ")
+                )
+            }
+            Error::AliasNotConnected {
+                alias,
+                target,
+                screens,
+            } => {
+                write!(
+                    f,
+                    "Screen alias {:?} refers to {:?}, which is not connected.",
+                    alias, target
+                )?;
+                write_screens(f, screens)
+            }
+            Error::UnknownArea {
+                area,
+                screen,
+                areas,
+            } if areas.is_empty() => write!(
+                f,
+                "Unknown area {:?}: there is no template for screen {} in the config.",
+                area, screen
+            ),
+            Error::UnknownArea {
+                area,
+                screen,
+                areas,
+            } => write!(
+                f,
+                "Unknown area {:?}. Areas of the template for screen {}: {}.",
+                area,
+                screen,
+                areas.join(", ")
+            ),
+            Error::NeedsWindow(c) => write!(
+                f,
+                "Screen selector {:?} needs a window to be relative to.",
+                c
+            ),
+            Error::NoScreens => write!(f, "No screens found."),
+            Error::NoMatchingWindow(selector) => write!(
+                f,
+                "No window matches {:?}. `windows` lists the open windows.",
+                selector
+            ),
+            #[cfg(target_os = "macos")]
+            Error::AccessibilityNotGranted => write!(
+                f,
+                "Accessibility permissions not granted. Please enable them in System Settings."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn write_screens(f: &mut fmt::Formatter<'_>, screens: &[Screen]) -> fmt::Result {
+    write!(f, " Available screens:")?;
+    for s in screens {
+        write!(
+            f,
+            "\n  {}: {} ({}), {}x{} at ({}, {})",
+            s.index, s.name, s.id, s.frame.width, s.frame.height, s.frame.x, s.frame.y
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use crate::command::{Anchor, Length, MoveParameters, Placement, ScreenSelector};
+use crate::geometry::weighted_edge;
+
+/// Describes `placement` in words, e.g. `screen 0, columns 2–3 of 3, row 2 of 3` for `031-231`.
+pub fn describe(placement: &Placement) -> String {
+    match placement {
+        Placement::Grid(params) => describe_grid(params),
+        Placement::Sized(sized) => format!(
+            "{}, {} wide and {} high, at the {}",
+            describe_screen(&sized.screen),
+            describe_length(sized.width),
+            describe_length(sized.height),
+            describe_anchor(sized.anchor)
+        ),
+        Placement::Area(area) => format!(
+            "{}, area {:?} of the screen's template",
+            describe_screen(&area.screen),
+            area.name
+        ),
+    }
+}
+
+/// Describes the cells of a grid, e.g. `screen 0, columns 2–3 of 3, row 2 of 3`.
+pub fn describe_grid(params: &MoveParameters) -> String {
+    format!(
+        "{}, {}, {}",
+        describe_screen(&params.screen),
+        describe_span(
+            ("column", "columns", "full width"),
+            params.x_ratio,
+            &params.x_weights,
+            params.x_start,
+            params.x_end
+        ),
+        describe_span(
+            ("row", "rows", "full height"),
+            params.y_ratio,
+            &params.y_weights,
+            params.y_start,
+            params.y_end
+        )
+    )
+}
+
+/// Grids with more parts than this along an axis are too large to draw.
+pub const MAX_DRAWN_PARTS: i32 = 24;
+
+/// Draws the grid of `params` with the cells it covers filled in, e.g. for `031-231`:
+///
+/// ```text
+/// +---+---+---+
+/// |   |   |   |
+/// +---+---+---+
+/// |   |###|###|
+/// +---+---+---+
+/// |   |   |   |
+/// +---+---+---+
+/// ```
+///
+/// Weighted parts are drawn as wide or as high as their weights say, as far as characters allow.
+/// Returns `None` for grids with more than `MAX_DRAWN_PARTS` parts along an axis.
+pub fn render_grid(params: &MoveParameters) -> Option<String> {
+    if params.x_ratio > MAX_DRAWN_PARTS || params.y_ratio > MAX_DRAWN_PARTS {
+        return None;
+    }
+    // Equal parts are 3 characters wide and 1 line high, plus the border.
+    let widths = cell_sizes(params.x_ratio, &params.x_weights, 4, 6);
+    let heights = cell_sizes(params.y_ratio, &params.y_weights, 2, 3);
+    let line = widths
+        .iter()
+        .fold("+".to_string(), |line, w| line + &"-".repeat(*w) + "+")
+        + "\n";
+    let mut rv = line.clone();
+    for (y, height) in heights.iter().enumerate() {
+        let mut row = "|".to_string();
+        for (x, width) in widths.iter().enumerate() {
+            let covered = (params.x_start..=params.x_end).contains(&(x as i32))
+                && (params.y_start..=params.y_end).contains(&(y as i32));
+            row.push_str(&(if covered { "#" } else { " " }).repeat(*width));
+            row.push('|');
+        }
+        row.push('\n');
+        rv.push_str(&row.repeat(*height));
+        rv.push_str(&line);
+    }
+    Some(rv)
+}
+
+/// The sizes in characters of the `ratio` parts of an axis, not counting the borders. Equal parts
+/// take `step` characters each, weighted ones share `weighted_step` characters per part.
+fn cell_sizes(ratio: i32, weights: &[i32], step: i32, weighted_step: i32) -> Vec<usize> {
+    let length = if weights.is_empty() {
+        step * ratio
+    } else {
+        weighted_step * ratio
+    };
+    (0..ratio)
+        .map(|k| {
+            let start = weighted_edge(0, length, ratio, weights, k);
+            let end = weighted_edge(0, length, ratio, weights, k + 1);
+            (end - start - 1).max(1) as usize
+        })
+        .collect()
+}
+
+fn describe_screen(screen: &ScreenSelector) -> String {
+    let side = |c: char| match c {
+        'l' => Some("left"),
+        'r' => Some("right"),
+        't' | 'u' => Some("top"),
+        'b' | 'd' => Some("bottom"),
+        _ => None,
+    };
+    match *screen {
+        ScreenSelector::Index(index) => format!("screen {}", index),
+        ScreenSelector::Char('m' | 'c') => "the main screen".to_string(),
+        ScreenSelector::Char('s') => "the screen the window is on".to_string(),
+        ScreenSelector::Char('n') => "the screen right of the window's screen".to_string(),
+        ScreenSelector::Char('p') => "the screen left of the window's screen".to_string(),
+        ScreenSelector::Char(c) => match side(c) {
+            Some(side) => format!("the {}most screen", side),
+            None => format!("screen {:?}", c),
+        },
+        ScreenSelector::Nth(c, n) => match side(c) {
+            Some(side) => format!("screen {} from the {} (counting from 0)", n, side),
+            None => format!("screen {}{}", c, n),
+        },
+        ScreenSelector::Alias(ref alias) => format!("screen {:?}", alias),
+    }
+}
+
+/// Describes the parts `start` to `end` of `ratio` parts, counting from 1 like people do. `names`
+/// are the singular and plural name of a part and the name for all of them.
+fn describe_span(
+    names: (&str, &str, &str),
+    ratio: i32,
+    weights: &[i32],
+    start: i32,
+    end: i32,
+) -> String {
+    let (one, many, all) = names;
+    let mut rv = if ratio == 1 {
+        all.to_string()
+    } else if start == end {
+        format!("{} {} of {}", one, start + 1, ratio)
+    } else {
+        format!("{} {}–{} of {}", many, start + 1, end + 1, ratio)
+    };
+    if ratio > 1 && !weights.is_empty() {
+        let weights = weights.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        rv.push_str(&format!(" weighted {}", weights.join(":")));
+    }
+    rv
+}
+
+fn describe_length(length: Length) -> String {
+    match length {
+        Length::Pixels(p) => format!("{} pixels", p),
+        Length::Percent(p) => format!("{}%", p),
+    }
+}
+
+fn describe_anchor(anchor: Anchor) -> &'static str {
+    match anchor {
+        Anchor::TopLeft => "top left corner",
+        Anchor::Top => "top edge",
+        Anchor::TopRight => "top right corner",
+        Anchor::Left => "left edge",
+        Anchor::Center => "center",
+        Anchor::Right => "right edge",
+        Anchor::BottomLeft => "bottom left corner",
+        Anchor::Bottom => "bottom edge",
+        Anchor::BottomRight => "bottom right corner",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(command: &str) -> Option<String> {
+        render_grid(&MoveParameters::from_command(command).unwrap())
+    }
+
+    #[test]
+    fn draws_the_covered_cells() {
+        assert_eq!(
+            render("031-231").unwrap(),
+            "+---+---+---+\n\
+             |   |   |   |\n\
+             +---+---+---+\n\
+             |   |###|###|\n\
+             +---+---+---+\n\
+             |   |   |   |\n\
+             +---+---+---+\n"
+        );
+    }
+
+    #[test]
+    fn draws_weighted_parts_to_scale() {
+        assert_eq!(
+            render("0:3,1,1:1-2").unwrap(),
+            "+---------+---+---+\n\
+             |         |###|###|\n\
+             +---------+---+---+\n"
+        );
+        assert_eq!(
+            render("0:1:0:2,1:0").unwrap(),
+            "+---+\n\
+             |###|\n\
+             |###|\n\
+             |###|\n\
+             +---+\n\
+             |   |\n\
+             +---+\n"
+        );
+    }
+
+    #[test]
+    fn large_grids_are_not_drawn() {
+        assert!(render("0:24:23:24:0").is_some());
+        assert_eq!(render("0:25:0"), None);
+        assert_eq!(render("0:1:0:5000:0"), None);
+        assert_eq!(render("0:2000000000:0"), None);
+    }
+}
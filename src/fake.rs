@@ -0,0 +1,173 @@
+use crate::Rect;
+use crate::backend::{Screen, Window, WindowBackend, WindowRef};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// A scripted desktop, usually read from a JSON fixture.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FakeDesktop {
+    pub screens: Vec<Screen>,
+    #[serde(default)]
+    pub windows: Vec<Window>,
+    /// `number` of the focused window in `windows`.
+    #[serde(default)]
+    pub focused: Option<u32>,
+    #[serde(default)]
+    pub space_id: u64,
+}
+
+/// An in-memory backend that does not touch any real window. Used to run the commands on machines
+/// without a supported window system, i.e. in CI.
+pub struct FakeBackend {
+    desktop: FakeDesktop,
+    /// If set, every change to the desktop is written back to this file, so that the next
+    /// invocation sees it.
+    save_to: Option<PathBuf>,
+}
+
+impl FakeBackend {
+    pub fn new(desktop: FakeDesktop) -> Self {
+        FakeBackend {
+            desktop,
+            save_to: None,
+        }
+    }
+
+    /// Loads the desktop from the JSON fixture at `path`. Changes are only written back to the
+    /// fixture if `write_back` is set.
+    pub fn load(path: impl Into<PathBuf>, write_back: bool) -> Result<Self> {
+        let path = path.into();
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read fixture {}", path.display()))?;
+        let desktop: FakeDesktop =
+            serde_json::from_str(&data).context("Failed to parse fixture as JSON")?;
+        let mut backend = FakeBackend::new(desktop);
+        backend.save_to = write_back.then_some(path);
+        Ok(backend)
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.save_to else {
+            return Ok(());
+        };
+        let data =
+            serde_json::to_string_pretty(&self.desktop).context("Failed to serialize fixture")?;
+        fs::write(path, data).context("Failed to write fixture")?;
+        Ok(())
+    }
+
+    fn find_window(&self, window: &WindowRef) -> Result<usize> {
+        let number = match window.number {
+            Some(n) => n,
+            None => match self.desktop.focused {
+                Some(n) => n,
+                None => bail!("No window has the focus."),
+            },
+        };
+        match self
+            .desktop
+            .windows
+            .iter()
+            .position(|w| w.number == number && w.owner_pid == window.pid)
+        {
+            Some(idx) => Ok(idx),
+            None => bail!("Unknown window {} of pid {}.", number, window.pid),
+        }
+    }
+}
+
+impl WindowBackend for FakeBackend {
+    fn screens(&self) -> Result<Vec<Screen>> {
+        Ok(self.desktop.screens.clone())
+    }
+
+    fn focused_window(&self) -> Result<WindowRef> {
+        let Some(number) = self.desktop.focused else {
+            bail!("No window has the focus.");
+        };
+        match self.desktop.windows.iter().find(|w| w.number == number) {
+            Some(w) => Ok(WindowRef {
+                pid: w.owner_pid,
+                number: Some(w.number),
+            }),
+            None => bail!("Focused window {} does not exist.", number),
+        }
+    }
+
+    fn window_list(&self, all_windows: bool) -> Result<Vec<Window>> {
+        Ok(self
+            .desktop
+            .windows
+            .iter()
+            .filter(|w| all_windows || w.on_screen)
+            .cloned()
+            .collect())
+    }
+
+    fn get_frame(&self, window: &WindowRef) -> Result<Rect> {
+        let idx = self.find_window(window)?;
+        Ok(self.desktop.windows[idx].bounds.clone())
+    }
+
+    fn set_frame(&mut self, window: &WindowRef, frame: &Rect) -> Result<()> {
+        let idx = self.find_window(window)?;
+        self.desktop.windows[idx].bounds = frame.clone();
+        self.save()
+    }
+
+    fn current_space_id(&self) -> Result<u64> {
+        Ok(self.desktop.space_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), include_str!("../tests/fixtures/desktop.json")).unwrap();
+        file
+    }
+
+    fn move_focused(backend: &mut FakeBackend) -> Rect {
+        let window = backend.focused_window().unwrap();
+        let frame = Rect {
+            x: 0,
+            y: 25,
+            width: 960,
+            height: 1055,
+        };
+        backend.set_frame(&window, &frame).unwrap();
+        assert_eq!(backend.get_frame(&window).unwrap(), frame);
+        frame
+    }
+
+    #[test]
+    fn changes_stay_in_memory() {
+        let file = fixture();
+        let before = fs::read_to_string(file.path()).unwrap();
+        move_focused(&mut FakeBackend::load(file.path(), false).unwrap());
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), before);
+    }
+
+    #[test]
+    fn changes_are_written_back_if_asked_for() {
+        let file = fixture();
+        let frame = move_focused(&mut FakeBackend::load(file.path(), true).unwrap());
+        let reloaded = FakeBackend::load(file.path(), false).unwrap();
+        assert_eq!(reloaded.desktop.windows[0].bounds, frame);
+    }
+
+    #[test]
+    fn unknown_windows_are_errors() {
+        let backend = FakeBackend::load(fixture().path(), false).unwrap();
+        let window = WindowRef {
+            pid: 10,
+            number: Some(2),
+        };
+        assert!(backend.get_frame(&window).is_err());
+    }
+}
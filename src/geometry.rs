@@ -0,0 +1,528 @@
+use crate::Rect;
+use crate::command::{Length, MoveParameters, ScreenSelector, SizedPlacement};
+use serde::{Deserialize, Serialize};
+
+/// Space to keep free at each edge of a rectangle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Insets {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+impl Insets {
+    pub fn uniform(v: i32) -> Self {
+        Insets {
+            top: v,
+            right: v,
+            bottom: v,
+            left: v,
+        }
+    }
+}
+
+impl Rect {
+    /// Returns this rect shrunk by `insets`. Insets larger than the rect leave 1 pixel of it, since
+    /// windows cannot be smaller.
+    pub fn inset(&self, insets: &Insets) -> Rect {
+        Rect {
+            x: self.x + insets.left.min(self.width - 1).max(0),
+            y: self.y + insets.top.min(self.height - 1).max(0),
+            width: (self.width - insets.left - insets.right).max(1),
+            height: (self.height - insets.top - insets.bottom).max(1),
+        }
+    }
+
+    /// Returns true if no edge of this rect is more than `tolerance` pixels away from the same edge
+    /// of `other`.
+    pub fn approx_eq(&self, other: &Rect, tolerance: i32) -> bool {
+        (self.x - other.x).abs() <= tolerance
+            && (self.y - other.y).abs() <= tolerance
+            && (self.x + self.width - other.x - other.width).abs() <= tolerance
+            && (self.y + self.height - other.y - other.height).abs() <= tolerance
+    }
+
+    /// Returns the rect that this rect and `other` have in common, if they overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let width = (self.x + self.width).min(other.x + other.width) - x;
+        let height = (self.y + self.height).min(other.y + other.height) - y;
+        (width > 0 && height > 0).then_some(Rect {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// Returns the area in pixels that this rect and `other` have in common.
+    pub fn overlap_area(&self, other: &Rect) -> i64 {
+        let width = (self.x + self.width).min(other.x + other.width) - self.x.max(other.x);
+        let height = (self.y + self.height).min(other.y + other.height) - self.y.max(other.y);
+        i64::from(width.max(0)) * i64::from(height.max(0))
+    }
+}
+
+/// Returns the `k`th of the `parts + 1` edges that split `length` pixels starting at `origin` into
+/// `parts` parts. Edges are floored to whole pixels, so the remainder pixels are spread evenly
+/// over the parts, the first edge is `origin` and the last is `origin + length`. Since adjacent
+/// tiles share the edge between them, tiles of any grid cover the area exactly, without overlaps
+/// or seams.
+pub fn partition_edge(origin: i32, length: i32, parts: i32, k: i32) -> i32 {
+    let offset = (i64::from(length) * i64::from(k)).div_euclid(i64::from(parts));
+    origin + offset as i32
+}
+
+/// Like `partition_edge`, but part `i` gets `weights[i]` shares of `length`. Without weights, all
+/// `parts` parts are equally large. Edges are floored the same way, so weighted tiles cover the
+/// area exactly, too.
+pub fn weighted_edge(origin: i32, length: i32, parts: i32, weights: &[i32], k: i32) -> i32 {
+    if weights.is_empty() {
+        return partition_edge(origin, length, parts, k);
+    }
+    let total = weights.iter().sum();
+    let before = weights[..k as usize].iter().sum();
+    partition_edge(origin, length, total, before)
+}
+
+/// Computes the frame for `params` inside of `area`. Tiles are separated by `gap` pixels, so that
+/// adjacent tiles end up exactly `gap` apart, while edges touching the border of `area` are left
+/// alone.
+pub fn compute_frame(area: &Rect, params: &MoveParameters, gap: i32) -> Rect {
+    let x_edge = |k| weighted_edge(area.x, area.width, params.x_ratio, &params.x_weights, k);
+    let y_edge = |k| weighted_edge(area.y, area.height, params.y_ratio, &params.y_weights, k);
+    let left = x_edge(params.x_start);
+    let right = x_edge(params.x_end + 1);
+    let top = y_edge(params.y_start);
+    let bottom = y_edge(params.y_end + 1);
+    let frame = Rect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    };
+
+    // Every inner edge gives half the gap to each of the two tiles sharing it.
+    let leading = gap / 2;
+    let trailing = gap - leading;
+    let insets = Insets {
+        top: if params.y_start > 0 { leading } else { 0 },
+        right: if params.x_end + 1 < params.x_ratio {
+            trailing
+        } else {
+            0
+        },
+        bottom: if params.y_end + 1 < params.y_ratio {
+            trailing
+        } else {
+            0
+        },
+        left: if params.x_start > 0 { leading } else { 0 },
+    };
+    frame.inset(&insets)
+}
+
+/// The largest number of parts `nearest_grid` tries along each axis.
+const MAX_PARTS: i32 = 12;
+
+/// A grid placement found for a frame and how far its frame is off.
+#[derive(Debug, Clone)]
+pub struct GridMatch {
+    pub params: MoveParameters,
+    /// the largest distance in pixels between an edge of the placement's frame and the same edge
+    /// of the frame looked for
+    pub error: i32,
+}
+
+/// Finds the grid placement on `screen` whose frame inside `area` matches `frame`. Along each axis,
+/// the smallest number of parts is used that has a span whose edges are at most `tolerance` pixels
+/// off. If there is none, the span with the smallest error is used.
+pub fn nearest_grid(
+    area: &Rect,
+    frame: &Rect,
+    screen: ScreenSelector,
+    gap: i32,
+    tolerance: i32,
+) -> GridMatch {
+    let mut params = MoveParameters {
+        screen,
+        x_ratio: 1,
+        y_ratio: 1,
+        x_weights: Vec::new(),
+        y_weights: Vec::new(),
+        x_start: 0,
+        x_end: 0,
+        y_start: 0,
+        y_end: 0,
+    };
+    let ((x_ratio, x_start, x_end), x_error) = nearest_span(
+        (frame.x, frame.x + frame.width),
+        tolerance,
+        |ratio, start, end| {
+            let mut p = params.clone();
+            (p.x_ratio, p.x_start, p.x_end) = (ratio, start, end);
+            let f = compute_frame(area, &p, gap);
+            (f.x, f.x + f.width)
+        },
+    );
+    let ((y_ratio, y_start, y_end), y_error) = nearest_span(
+        (frame.y, frame.y + frame.height),
+        tolerance,
+        |ratio, start, end| {
+            let mut p = params.clone();
+            (p.y_ratio, p.y_start, p.y_end) = (ratio, start, end);
+            let f = compute_frame(area, &p, gap);
+            (f.y, f.y + f.height)
+        },
+    );
+    (params.x_ratio, params.x_start, params.x_end) = (x_ratio, x_start, x_end);
+    (params.y_ratio, params.y_start, params.y_end) = (y_ratio, y_start, y_end);
+    GridMatch {
+        params,
+        error: x_error.max(y_error),
+    }
+}
+
+/// Finds the `(ratio, start, end)` span along one axis whose `edges` are closest to `target`,
+/// trying ratios in increasing order and stopping at the first one that is within `tolerance`.
+fn nearest_span(
+    target: (i32, i32),
+    tolerance: i32,
+    edges: impl Fn(i32, i32, i32) -> (i32, i32),
+) -> ((i32, i32, i32), i32) {
+    let mut best = ((1, 0, 0), i32::MAX);
+    for ratio in 1..=MAX_PARTS {
+        for start in 0..ratio {
+            for end in start..ratio {
+                let (first, last) = edges(ratio, start, end);
+                let error = (first - target.0).abs().max((last - target.1).abs());
+                if error < best.1 {
+                    best = ((ratio, start, end), error);
+                }
+            }
+        }
+        // Earlier ratios had nothing within tolerance, so a match must be from this one.
+        if best.1 <= tolerance {
+            break;
+        }
+    }
+    best
+}
+
+/// Computes the frame for a `sized` placement inside of `area`. Sizes larger than `area` are
+/// clamped to it.
+pub fn sized_frame(area: &Rect, sized: &SizedPlacement) -> Rect {
+    let length = |l: Length, available: i32| {
+        let v = match l {
+            Length::Pixels(p) => p,
+            Length::Percent(p) => partition_edge(0, available, 100, p),
+        };
+        v.clamp(1, available.max(1))
+    };
+    let width = length(sized.width, area.width);
+    let height = length(sized.height, area.height);
+    let (x_align, y_align) = sized.anchor.alignment();
+    Rect {
+        x: area.x + (area.width - width) * x_align / 2,
+        y: area.y + (area.height - height) * y_align / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Anchor;
+    use proptest::prelude::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+
+    fn grid(x_ratio: i32, x_start: i32, x_end: i32) -> MoveParameters {
+        MoveParameters {
+            screen: ScreenSelector::Index(0),
+            x_ratio,
+            y_ratio: 1,
+            x_weights: Vec::new(),
+            y_weights: Vec::new(),
+            x_start,
+            x_end,
+            y_start: 0,
+            y_end: 0,
+        }
+    }
+
+    #[test]
+    fn gaps_separate_adjacent_tiles() {
+        let left = compute_frame(&AREA, &grid(2, 0, 0), 10);
+        let right = compute_frame(&AREA, &grid(2, 1, 1), 10);
+        assert_eq!((left.x, left.width), (0, 955));
+        assert_eq!((right.x, right.width), (965, 955));
+    }
+
+    #[test]
+    fn huge_gaps_leave_a_pixel() {
+        let frame = compute_frame(&AREA, &grid(2, 1, 1), 2000);
+        assert_eq!((frame.x, frame.width), (1919, 1));
+        assert_eq!(frame.height, 1080);
+    }
+
+    #[test]
+    fn weighted_tiles_cover_odd_widths_exactly() {
+        let area = Rect {
+            x: 7,
+            y: 0,
+            width: 1921,
+            height: 1080,
+        };
+        let mut params = grid(3, 0, 0);
+        params.x_weights = vec![3, 1, 1];
+        let tiles = (0..3)
+            .map(|k| {
+                (params.x_start, params.x_end) = (k, k);
+                let f = compute_frame(&area, &params, 0);
+                (f.x, f.x + f.width)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tiles, [(7, 1159), (1159, 1543), (1543, 1928)]);
+        (params.x_start, params.x_end) = (1, 2);
+        let span = compute_frame(&area, &params, 0);
+        assert_eq!((span.x, span.x + span.width), (1159, 1928));
+    }
+
+    fn sized(width: Length, height: Length, anchor: Anchor) -> SizedPlacement {
+        SizedPlacement {
+            screen: ScreenSelector::Index(0),
+            width,
+            height,
+            anchor,
+        }
+    }
+
+    #[test]
+    fn sized_frames_stick_to_their_anchor() {
+        let area = Rect {
+            x: 100,
+            y: 50,
+            width: 1000,
+            height: 800,
+        };
+        let (width, height) = (Length::Pixels(200), Length::Pixels(100));
+        for (anchor, x, y) in [
+            (Anchor::TopLeft, 100, 50),
+            (Anchor::Top, 500, 50),
+            (Anchor::TopRight, 900, 50),
+            (Anchor::Left, 100, 400),
+            (Anchor::Center, 500, 400),
+            (Anchor::Right, 900, 400),
+            (Anchor::BottomLeft, 100, 750),
+            (Anchor::Bottom, 500, 750),
+            (Anchor::BottomRight, 900, 750),
+        ] {
+            let frame = sized_frame(&area, &sized(width, height, anchor));
+            let expected = Rect {
+                x,
+                y,
+                width: 200,
+                height: 100,
+            };
+            assert_eq!(frame, expected, "{:?}", anchor);
+        }
+    }
+
+    #[test]
+    fn sized_frames_are_clamped_to_the_area() {
+        let percent = sized(Length::Percent(50), Length::Percent(25), Anchor::Center);
+        assert_eq!(
+            sized_frame(&AREA, &percent),
+            Rect {
+                x: 480,
+                y: 405,
+                width: 960,
+                height: 270,
+            }
+        );
+        let huge = Length::Pixels(5000);
+        for anchor in [Anchor::TopLeft, Anchor::Center, Anchor::BottomRight] {
+            assert_eq!(sized_frame(&AREA, &sized(huge, huge, anchor)), AREA);
+        }
+        let full = sized(Length::Percent(100), Length::Pixels(1080), Anchor::Bottom);
+        assert_eq!(sized_frame(&AREA, &full), AREA);
+        let tiny = Rect {
+            x: 10,
+            y: 10,
+            width: 0,
+            height: 0,
+        };
+        let frame = sized_frame(&tiny, &sized(huge, huge, Anchor::BottomRight));
+        assert_eq!((frame.width, frame.height), (1, 1));
+    }
+
+    fn span(params: &MoveParameters) -> (i32, i32, i32, i32, i32, i32) {
+        (
+            params.x_ratio,
+            params.x_start,
+            params.x_end,
+            params.y_ratio,
+            params.y_start,
+            params.y_end,
+        )
+    }
+
+    #[test]
+    fn exact_matches_use_the_fewest_parts() {
+        let right_half = Rect {
+            x: 960,
+            y: 0,
+            width: 960,
+            height: 1080,
+        };
+        let found = nearest_grid(&AREA, &right_half, ScreenSelector::Index(0), 0, 0);
+        assert_eq!(found.params.to_compact().as_deref(), Some("021"));
+        assert_eq!(found.error, 0);
+
+        // The same edges are a span of a grid with 4 parts, too, but 2 come first.
+        let edges = |ratio, start, end| (start * 120 / ratio, (end + 1) * 120 / ratio);
+        assert_eq!(nearest_span((60, 120), 0, edges), ((2, 1, 1), 0));
+        assert_eq!(nearest_span((90, 120), 0, edges), ((4, 3, 3), 0));
+    }
+
+    #[test]
+    fn matches_within_tolerance_count_as_exact() {
+        let nearly_right_half = Rect {
+            x: 965,
+            y: 3,
+            width: 950,
+            height: 1075,
+        };
+        let found = nearest_grid(&AREA, &nearly_right_half, ScreenSelector::Index(0), 0, 10);
+        assert_eq!(span(&found.params), (2, 1, 1, 1, 0, 0));
+        assert_eq!(found.error, 5);
+    }
+
+    #[test]
+    fn frames_off_the_grid_get_the_nearest_span_and_its_error() {
+        let frame = Rect {
+            x: 100,
+            y: 0,
+            width: 500,
+            height: 1080,
+        };
+        let found = nearest_grid(&AREA, &frame, ScreenSelector::Char('m'), 0, 10);
+        // Twelfths 1 to 3 span 160 to 640.
+        assert_eq!(span(&found.params), (12, 1, 3, 1, 0, 0));
+        assert_eq!(found.error, 60);
+        assert_eq!(found.params.screen, ScreenSelector::Char('m'));
+    }
+
+    #[test]
+    fn gaps_are_taken_into_account() {
+        // With a gap of 10, the right half starts 5 pixels right of the middle.
+        let right_half = Rect {
+            x: 965,
+            y: 0,
+            width: 955,
+            height: 1080,
+        };
+        let found = nearest_grid(&AREA, &right_half, ScreenSelector::Index(0), 10, 0);
+        assert_eq!(span(&found.params), (2, 1, 1, 1, 0, 0));
+        assert_eq!(found.error, 0);
+        let top_left_quarter = Rect {
+            x: 0,
+            y: 0,
+            width: 955,
+            height: 535,
+        };
+        let found = nearest_grid(&AREA, &top_left_quarter, ScreenSelector::Index(0), 10, 0);
+        assert_eq!(span(&found.params), (2, 0, 0, 2, 0, 0));
+        assert_eq!(found.error, 0);
+    }
+
+    proptest! {
+        #[test]
+        fn edges_span_the_length_in_order(
+            origin in -10_000..10_000,
+            length in 0..10_000,
+            parts in 1..200,
+        ) {
+            prop_assert_eq!(partition_edge(origin, length, parts, 0), origin);
+            prop_assert_eq!(partition_edge(origin, length, parts, parts), origin + length);
+            for k in 0..parts {
+                prop_assert!(
+                    partition_edge(origin, length, parts, k)
+                        <= partition_edge(origin, length, parts, k + 1)
+                );
+            }
+        }
+
+        #[test]
+        fn adjacent_tiles_share_an_edge(
+            x in -5_000..5_000,
+            ratio in 1..100,
+            extra in 0..10_000,
+        ) {
+            // Tiles narrower than a pixel are widened to one, see `Rect::inset`.
+            let area = Rect { x, y: 0, width: ratio + extra, height: 100 };
+            let tiles = (0..ratio)
+                .map(|k| compute_frame(&area, &grid(ratio, k, k), 0))
+                .collect::<Vec<_>>();
+            prop_assert_eq!(tiles[0].x, area.x);
+            let last = &tiles[tiles.len() - 1];
+            prop_assert_eq!(last.x + last.width, area.x + area.width);
+            for pair in tiles.windows(2) {
+                prop_assert_eq!(pair[0].x + pair[0].width, pair[1].x);
+            }
+        }
+
+        #[test]
+        fn spans_end_where_their_last_tile_ends(
+            ratio in 1..50,
+            extra in 0..10_000,
+            start in 0..50i32,
+            len in 0..50,
+        ) {
+            let width = ratio + extra;
+            let start = start % ratio;
+            let end = i32::min(start + len, ratio - 1);
+            let area = Rect { x: 0, y: 0, width, height: 100 };
+            let span = compute_frame(&area, &grid(ratio, start, end), 0);
+            let first = compute_frame(&area, &grid(ratio, start, start), 0);
+            let last = compute_frame(&area, &grid(ratio, end, end), 0);
+            prop_assert_eq!(span.x, first.x);
+            prop_assert_eq!(span.x + span.width, last.x + last.width);
+        }
+
+        #[test]
+        fn weighted_tiles_cover_the_area(
+            x in -5_000..5_000,
+            weights in prop::collection::vec(1..20, 1..10),
+            extra in 0..10_000,
+        ) {
+            let total: i32 = weights.iter().sum();
+            let area = Rect { x, y: 0, width: total + extra, height: 100 };
+            let ratio = weights.len() as i32;
+            let mut params = grid(ratio, 0, 0);
+            params.x_weights = weights;
+            let tiles = (0..ratio)
+                .map(|k| {
+                    (params.x_start, params.x_end) = (k, k);
+                    compute_frame(&area, &params, 0)
+                })
+                .collect::<Vec<_>>();
+            prop_assert_eq!(tiles[0].x, area.x);
+            let last = &tiles[tiles.len() - 1];
+            prop_assert_eq!(last.x + last.width, area.x + area.width);
+            for pair in tiles.windows(2) {
+                prop_assert_eq!(pair[0].x + pair[0].width, pair[1].x);
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::Rect;
+use crate::backend::WindowRef;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many moves are remembered for undo and for redo.
+const MAX_ENTRIES: usize = 50;
+
+/// A window and the frame it had at some point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub window: WindowRef,
+    pub owner_name: String,
+    pub title: Option<String>,
+    pub frame: Rect,
+    /// seconds since the epoch when the entry was recorded
+    pub time: u64,
+}
+
+impl Entry {
+    pub fn new(window: WindowRef, owner_name: String, title: Option<String>, frame: Rect) -> Self {
+        Entry {
+            window,
+            owner_name,
+            title,
+            frame,
+            time: now(),
+        }
+    }
+
+    /// Returns a copy of this entry for the same window with `frame` recorded now.
+    pub fn with_frame(&self, frame: Rect) -> Self {
+        Entry::new(
+            self.window.clone(),
+            self.owner_name.clone(),
+            self.title.clone(),
+            frame,
+        )
+    }
+}
+
+/// The frames windows had before they were moved, so that moves can be undone.
+#[derive(Serialize, Deserialize, Default)]
+pub struct History {
+    /// frames to restore on undo, the most recent last
+    pub undo: Vec<Entry>,
+    /// frames to restore on redo, the most recent last
+    pub redo: Vec<Entry>,
+}
+
+impl History {
+    /// Loads the history from the JSON file.
+    pub fn load() -> Result<Self> {
+        let history_file = history_file_path()?;
+        if !history_file.exists() {
+            return Ok(History::default());
+        }
+        let data = fs::read_to_string(&history_file).context("Failed to read history file")?;
+        let history: History =
+            serde_json::from_str(&data).context("Failed to parse history file as JSON")?;
+        Ok(history)
+    }
+
+    /// Saves the history to the JSON file.
+    pub fn save(&self) -> Result<()> {
+        let history_file = history_file_path()?;
+        let data = serde_json::to_string_pretty(self).context("Failed to serialize history")?;
+        fs::write(&history_file, data).context("Failed to write history file")?;
+        Ok(())
+    }
+
+    /// Records the frame a window had before a new move. This invalidates everything that could
+    /// be redone.
+    pub fn record(&mut self, entry: Entry) {
+        push_bounded(&mut self.undo, entry);
+        self.redo.clear();
+    }
+
+    /// Takes the most recent entry to undo. `current` returns the current frame of the entry's
+    /// window, which turns it into the entry that redoes it, or `None` if the window was closed.
+    /// Entries of closed windows are dropped.
+    pub fn undo(
+        &mut self,
+        current: impl FnMut(&Entry) -> Result<Option<Rect>>,
+    ) -> Result<Option<Entry>> {
+        take(&mut self.undo, &mut self.redo, current)
+    }
+
+    /// Takes the most recent entry to redo, like `undo` the other way around.
+    pub fn redo(
+        &mut self,
+        current: impl FnMut(&Entry) -> Result<Option<Rect>>,
+    ) -> Result<Option<Entry>> {
+        take(&mut self.redo, &mut self.undo, current)
+    }
+}
+
+/// Pops the most recent entry of a window that is still open from `from` and pushes the entry
+/// that reverts it to `to`. If `current` fails, the entry stays where it was.
+fn take(
+    from: &mut Vec<Entry>,
+    to: &mut Vec<Entry>,
+    mut current: impl FnMut(&Entry) -> Result<Option<Rect>>,
+) -> Result<Option<Entry>> {
+    while let Some(entry) = from.pop() {
+        let frame = match current(&entry) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => continue,
+            Err(e) => {
+                from.push(entry);
+                return Err(e);
+            }
+        };
+        push_bounded(to, entry.with_frame(frame));
+        return Ok(Some(entry));
+    }
+    Ok(None)
+}
+
+/// Seconds since the epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn push_bounded(entries: &mut Vec<Entry>, entry: Entry) {
+    entries.push(entry);
+    if entries.len() > MAX_ENTRIES {
+        entries.remove(0);
+    }
+}
+
+/// Constructs the path to the history file.
+fn history_file_path() -> Result<PathBuf> {
+    Ok(crate::state_dir()?.join("history.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+
+    fn entry(number: u32) -> Entry {
+        let window = WindowRef {
+            pid: 1,
+            number: Some(number),
+        };
+        let frame = Rect {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 100,
+        };
+        Entry::new(window, String::new(), None, frame)
+    }
+
+    fn numbers(entries: &[Entry]) -> Vec<Option<u32>> {
+        entries.iter().map(|e| e.window.number).collect()
+    }
+
+    #[test]
+    fn undo_drops_entries_of_closed_windows() {
+        let mut history = History::default();
+        for number in [1, 2, 3] {
+            history.record(entry(number));
+        }
+        let undone = history
+            .undo(|e| Ok((e.window.number != Some(3)).then(|| e.frame.clone())))
+            .unwrap();
+        assert_eq!(undone.unwrap().window.number, Some(2));
+        assert_eq!(numbers(&history.undo), [Some(1)]);
+        assert_eq!(numbers(&history.redo), [Some(2)]);
+    }
+
+    #[test]
+    fn failures_keep_the_entry() {
+        let mut history = History::default();
+        history.record(entry(1));
+        assert!(history.undo(|_| bail!("backend failed")).is_err());
+        assert_eq!(numbers(&history.undo), [Some(1)]);
+        assert!(history.redo.is_empty());
+    }
+
+    #[test]
+    fn nothing_left_to_undo() {
+        let mut history = History::default();
+        history.record(entry(1));
+        assert!(history.undo(|_| Ok(None)).unwrap().is_none());
+        assert!(history.undo.is_empty());
+    }
+}
@@ -0,0 +1,505 @@
+use crate::Rect;
+use crate::backend::{Screen, Window, WindowBackend, WindowRef};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+#[derive(Deserialize)]
+struct WorkspaceRef {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Monitor {
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// in physical pixels
+    width: i32,
+    height: i32,
+    /// in logical pixels
+    x: i32,
+    y: i32,
+    scale: f64,
+    /// pixels reserved for bars at the left, top, right and bottom edge
+    #[serde(default)]
+    reserved: [i32; 4],
+    #[serde(default)]
+    transform: i32,
+    active_workspace: WorkspaceRef,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    id: i64,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Client {
+    /// the address of the window, a hex string like `0x5581c9e2a0f0`
+    address: String,
+    #[serde(default)]
+    mapped: bool,
+    #[serde(default)]
+    hidden: bool,
+    at: [i32; 2],
+    size: [i32; 2],
+    workspace: WorkspaceRef,
+    #[serde(default)]
+    floating: bool,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    title: String,
+    pid: i32,
+}
+
+impl Client {
+    /// The number we hand out for this window: the lower 32 bits of its address. Together with the
+    /// pid, this is unique in practice.
+    fn number(&self) -> Result<u32> {
+        let address = self.address.trim_start_matches("0x");
+        let address = u64::from_str_radix(address, 16)
+            .with_context(|| format!("Invalid window address {:?}", self.address))?;
+        Ok(address as u32)
+    }
+
+    fn frame(&self) -> Rect {
+        Rect {
+            x: self.at[0],
+            y: self.at[1],
+            width: self.size[0],
+            height: self.size[1],
+        }
+    }
+}
+
+/// The backend for Hyprland, controlled through its IPC socket. Moving a window makes it floating,
+/// since tiled windows cannot be placed freely.
+pub struct HyprlandBackend {
+    socket: PathBuf,
+}
+
+impl HyprlandBackend {
+    /// Finds the IPC socket of the instance named by `$HYPRLAND_INSTANCE_SIGNATURE`.
+    pub fn connect() -> Result<Self> {
+        let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+            .context("$HYPRLAND_INSTANCE_SIGNATURE is not set")?;
+        let socket = socket_dirs(std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from))
+            .into_iter()
+            .map(|dir| dir.join(&signature).join(".socket.sock"))
+            .find(|path| path.exists());
+        match socket {
+            Some(socket) => Ok(HyprlandBackend { socket }),
+            None => bail!("No IPC socket for Hyprland instance {}.", signature),
+        }
+    }
+
+    /// Sends `command` and returns the reply. Hyprland answers a single request per connection.
+    fn request(&self, command: &str) -> Result<String> {
+        let mut stream = UnixStream::connect(&self.socket).with_context(|| {
+            format!("Failed to connect to IPC socket {}", self.socket.display())
+        })?;
+        stream
+            .write_all(command.as_bytes())
+            .context("Failed to send IPC request")?;
+        let mut reply = String::new();
+        stream
+            .read_to_string(&mut reply)
+            .context("Failed to read IPC reply")?;
+        Ok(reply)
+    }
+
+    /// Runs the query `what`, e.g. `monitors`, and parses the JSON reply.
+    fn get<T: DeserializeOwned>(&self, what: &str) -> Result<T> {
+        let reply = self.request(&format!("j/{}", what))?;
+        serde_json::from_str(&reply).with_context(|| format!("Failed to parse {} reply", what))
+    }
+
+    /// Runs the dispatchers in `dispatches` as one batch.
+    fn dispatch(&self, dispatches: &[String]) -> Result<()> {
+        let batch = dispatches
+            .iter()
+            .map(|d| format!("dispatch {}", d))
+            .collect::<Vec<_>>()
+            .join(";");
+        let reply = self.request(&format!("[[BATCH]]{}", batch))?;
+        let results = reply.trim_end().split("\n\n").collect::<Vec<_>>();
+        if results.len() != dispatches.len() {
+            bail!(
+                "Expected {} results of the dispatches, got {:?}.",
+                dispatches.len(),
+                reply
+            );
+        }
+        for (dispatch, result) in dispatches.iter().zip(results) {
+            if result.trim() != "ok" {
+                bail!("Dispatch {:?} failed: {}", dispatch, result.trim());
+            }
+        }
+        Ok(())
+    }
+
+    fn find_client(&self, window: &WindowRef) -> Result<Client> {
+        let Some(number) = window.number else {
+            bail!("The Hyprland backend needs a window id.");
+        };
+        let clients: Vec<Client> = self.get("clients")?;
+        for client in clients {
+            if client.pid == window.pid && client.number()? == number {
+                return Ok(client);
+            }
+        }
+        bail!("Unknown window {} of pid {}.", number, window.pid)
+    }
+}
+
+/// The directories that may hold the sockets of Hyprland instances. Hyprland moved them from /tmp
+/// to the runtime directory in 0.40. Relative runtime directories are invalid and ignored.
+fn socket_dirs(runtime_dir: Option<PathBuf>) -> Vec<PathBuf> {
+    runtime_dir
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join("hypr"))
+        .into_iter()
+        .chain([PathBuf::from("/tmp/hypr")])
+        .collect()
+}
+
+impl WindowBackend for HyprlandBackend {
+    fn screens(&self) -> Result<Vec<Screen>> {
+        let monitors: Vec<Monitor> = self.get("monitors")?;
+        Ok(monitors
+            .into_iter()
+            .enumerate()
+            .map(|(index, m)| {
+                // Monitors report their mode in physical pixels, but positions in logical ones.
+                // Odd transforms rotate the monitor by 90 or 270 degrees.
+                let (width, height) = if m.transform % 2 == 1 {
+                    (m.height, m.width)
+                } else {
+                    (m.width, m.height)
+                };
+                let frame = Rect {
+                    x: m.x,
+                    y: m.y,
+                    width: (f64::from(width) / m.scale).round() as i32,
+                    height: (f64::from(height) / m.scale).round() as i32,
+                };
+                let [left, top, right, bottom] = m.reserved;
+                let visible_frame = Rect {
+                    x: frame.x + left,
+                    y: frame.y + top,
+                    width: frame.width - left - right,
+                    height: frame.height - top - bottom,
+                };
+                Screen {
+                    index: index as u64,
+                    id: if m.description.is_empty() {
+                        m.name.clone()
+                    } else {
+                        m.description
+                    },
+                    name: m.name,
+                    visible_frame,
+                    frame,
+                    scale_factor: m.scale,
+                }
+            })
+            .collect())
+    }
+
+    fn focused_window(&self) -> Result<WindowRef> {
+        // Without an active window, the reply is an empty object.
+        let reply: serde_json::Value = self.get("activewindow")?;
+        let Ok(client) = serde_json::from_value::<Client>(reply) else {
+            bail!("No window has the focus.");
+        };
+        Ok(WindowRef {
+            pid: client.pid,
+            number: Some(client.number()?),
+        })
+    }
+
+    fn window_list(&self, all_windows: bool) -> Result<Vec<Window>> {
+        let monitors: Vec<Monitor> = self.get("monitors")?;
+        let clients: Vec<Client> = self.get("clients")?;
+        let mut rv = Vec::new();
+        for client in clients.into_iter().filter(|c| c.mapped) {
+            let on_screen = !client.hidden
+                && monitors
+                    .iter()
+                    .any(|m| m.active_workspace.id == client.workspace.id);
+            if !all_windows && !on_screen {
+                continue;
+            }
+            rv.push(Window {
+                name: Some(client.title.clone()),
+                owner_pid: client.pid,
+                layer: 0,
+                number: client.number()?,
+                owner_name: client.class.clone(),
+                bounds: client.frame(),
+                alpha: 1.,
+                on_screen,
+            });
+        }
+        Ok(rv)
+    }
+
+    fn get_frame(&self, window: &WindowRef) -> Result<Rect> {
+        Ok(self.find_client(window)?.frame())
+    }
+
+    fn set_frame(&mut self, window: &WindowRef, frame: &Rect) -> Result<()> {
+        let client = self.find_client(window)?;
+        let target = format!("address:{}", client.address);
+        let mut dispatches = Vec::new();
+        if !client.floating {
+            dispatches.push(format!("setfloating {}", target));
+        }
+        dispatches.push(format!(
+            "resizewindowpixel exact {} {},{}",
+            frame.width, frame.height, target
+        ));
+        dispatches.push(format!(
+            "movewindowpixel exact {} {},{}",
+            frame.x, frame.y, target
+        ));
+        self.dispatch(&dispatches)
+    }
+
+    fn current_space_id(&self) -> Result<u64> {
+        let workspace: WorkspaceRef = self.get("activeworkspace")?;
+        match u64::try_from(workspace.id) {
+            Ok(id) => Ok(id),
+            Err(_) => bail!("Special workspaces have no id."),
+        }
+    }
+
+    fn space_name(&self, id: u64) -> Result<Option<String>> {
+        let workspaces: Vec<Workspace> = self.get("workspaces")?;
+        Ok(workspaces
+            .into_iter()
+            .find(|w| u64::try_from(w.id) == Ok(id))
+            .map(|w| w.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::thread::JoinHandle;
+
+    /// A 2560x1440 monitor turned by 90 degrees at scale 1.5 next to a plain 1920x1080 one.
+    const MONITORS: &str = r#"[
+        {"id": 0, "name": "DP-1", "description": "Dell Inc. DELL U2715H", "width": 2560, "height": 1440,
+         "x": 0, "y": 0, "scale": 1.5, "transform": 1, "reserved": [10, 30, 0, 5],
+         "activeWorkspace": {"id": 1, "name": "1"}},
+        {"id": 1, "name": "HDMI-A-1", "description": "", "width": 1920, "height": 1080,
+         "x": 960, "y": 0, "scale": 1.0, "transform": 0, "reserved": [0, 0, 0, 0],
+         "activeWorkspace": {"id": 2, "name": "2"}}
+    ]"#;
+
+    const CLIENTS: &str = r#"[
+        {"address": "0x5581c9e2a0f0", "mapped": true, "hidden": false, "at": [10, 30], "size": [950, 1672],
+         "workspace": {"id": 1, "name": "1"}, "floating": false, "class": "kitty", "title": "~", "pid": 10},
+        {"address": "0x5581c9e2b000", "mapped": true, "hidden": false, "at": [1000, 50], "size": [800, 600],
+         "workspace": {"id": 5, "name": "mail"}, "floating": true, "class": "thunderbird", "title": "Inbox",
+         "pid": 20},
+        {"address": "0x5581c9e2c000", "mapped": false, "hidden": false, "at": [0, 0], "size": [0, 0],
+         "workspace": {"id": 1, "name": "1"}, "floating": false, "class": "", "title": "", "pid": 30}
+    ]"#;
+
+    /// Answers `count` requests, one per connection like Hyprland, and returns them.
+    fn serve(
+        count: usize,
+        answer: fn(&str) -> String,
+    ) -> (HyprlandBackend, JoinHandle<Vec<String>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join(".socket.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = std::thread::spawn(move || {
+            let _dir = dir;
+            let mut requests = Vec::new();
+            for _ in 0..count {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let length = stream.read(&mut request).unwrap();
+                let request = String::from_utf8(request[..length].to_vec()).unwrap();
+                stream.write_all(answer(&request).as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (HyprlandBackend { socket }, server)
+    }
+
+    fn answer(request: &str) -> String {
+        match request {
+            "j/monitors" => MONITORS.to_string(),
+            "j/clients" => CLIENTS.to_string(),
+            "j/activewindow" => "{}".to_string(),
+            _ => panic!("unexpected request {:?}", request),
+        }
+    }
+
+    #[test]
+    fn screens_are_in_logical_pixels_without_reserved_space() {
+        let (backend, server) = serve(1, answer);
+        let screens = backend.screens().unwrap();
+        server.join().unwrap();
+
+        assert_eq!(screens[0].id, "Dell Inc. DELL U2715H");
+        assert_eq!(screens[0].scale_factor, 1.5);
+        assert_eq!(
+            screens[0].frame,
+            Rect {
+                x: 0,
+                y: 0,
+                width: 960,
+                height: 1707
+            }
+        );
+        // `reserved` is left, top, right and bottom.
+        assert_eq!(
+            screens[0].visible_frame,
+            Rect {
+                x: 10,
+                y: 30,
+                width: 950,
+                height: 1672
+            }
+        );
+        assert_eq!(screens[1].id, "HDMI-A-1");
+        assert_eq!(screens[1].frame, screens[1].visible_frame);
+        assert_eq!(
+            (screens[1].frame.width, screens[1].frame.height),
+            (1920, 1080)
+        );
+    }
+
+    #[test]
+    fn windows_are_the_mapped_clients() {
+        let (backend, server) = serve(5, answer);
+        let visible = backend.window_list(false).unwrap();
+        let all = backend.window_list(true).unwrap();
+        let focused = backend.focused_window();
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "j/monitors",
+                "j/clients",
+                "j/monitors",
+                "j/clients",
+                "j/activewindow"
+            ]
+        );
+
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].number, 0xc9e2a0f0);
+        assert_eq!(visible[0].owner_name, "kitty");
+        assert_eq!(all.len(), 2);
+        assert!(!all[1].on_screen);
+        assert_eq!(focused.unwrap_err().to_string(), "No window has the focus.");
+    }
+
+    #[test]
+    fn set_frame_floats_and_places_the_window_in_one_batch() {
+        let (mut backend, server) = serve(2, |request| match request {
+            "j/clients" => CLIENTS.to_string(),
+            _ => "ok\n\nok\n\nok".to_string(),
+        });
+        let window = WindowRef {
+            pid: 10,
+            number: Some(0xc9e2a0f0),
+        };
+        let frame = Rect {
+            x: 960,
+            y: 0,
+            width: 960,
+            height: 1080,
+        };
+        backend.set_frame(&window, &frame).unwrap();
+        assert_eq!(
+            server.join().unwrap()[1],
+            "[[BATCH]]dispatch setfloating address:0x5581c9e2a0f0;\
+             dispatch resizewindowpixel exact 960 1080,address:0x5581c9e2a0f0;\
+             dispatch movewindowpixel exact 960 0,address:0x5581c9e2a0f0"
+        );
+    }
+
+    #[test]
+    fn failed_dispatches_are_errors() {
+        let (mut backend, server) = serve(2, |request| match request {
+            "j/clients" => CLIENTS.to_string(),
+            _ => "ok\n\nInvalid window\n\nok".to_string(),
+        });
+        let window = WindowRef {
+            pid: 10,
+            number: Some(0xc9e2a0f0),
+        };
+        let frame = Rect {
+            x: 960,
+            y: 0,
+            width: 960,
+            height: 1080,
+        };
+        let err = backend.set_frame(&window, &frame).unwrap_err();
+        server.join().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Dispatch \"resizewindowpixel exact 960 1080,address:0x5581c9e2a0f0\" failed: Invalid \
+             window"
+        );
+    }
+
+    #[test]
+    fn unknown_windows_are_errors() {
+        let (backend, server) = serve(1, answer);
+        let window = WindowRef {
+            pid: 20,
+            number: Some(0xc9e2a0f0),
+        };
+        let err = backend.get_frame(&window).unwrap_err();
+        server.join().unwrap();
+        assert_eq!(err.to_string(), "Unknown window 3387072752 of pid 20.");
+    }
+
+    #[test]
+    fn missing_results_are_errors() {
+        let (backend, server) = serve(2, |request| match request {
+            "[[BATCH]]dispatch a;dispatch b;dispatch c" => "ok\n\nok".to_string(),
+            _ => "ok\n\nok\n\n".to_string(),
+        });
+        let dispatches = ["a", "b", "c"].map(str::to_string);
+        let err = backend.dispatch(&dispatches).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected 3 results of the dispatches, got \"ok\\n\\nok\"."
+        );
+        backend.dispatch(&dispatches[..2]).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn sockets_are_looked_for_in_the_runtime_directory_and_tmp() {
+        assert_eq!(
+            socket_dirs(Some(PathBuf::from("/run/user/1000"))),
+            [
+                PathBuf::from("/run/user/1000/hypr"),
+                PathBuf::from("/tmp/hypr")
+            ]
+        );
+        assert_eq!(socket_dirs(None), [PathBuf::from("/tmp/hypr")]);
+        assert_eq!(
+            socket_dirs(Some(PathBuf::from("run"))),
+            [PathBuf::from("/tmp/hypr")]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::Rect;
+use crate::axui;
+use crate::backend::{Screen, Window, WindowBackend, WindowRef};
+use crate::coords;
+use crate::skylight;
+use anyhow::{Result, bail};
+use cocoa::appkit::NSScreen;
+use cocoa::base::{BOOL, YES, id, nil};
+use cocoa::foundation::NSArray;
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::uuid::{CFUUID, CFUUIDCreateString, CFUUIDRef};
+use objc::runtime::Class;
+use objc::runtime::Object;
+use objc::{msg_send, sel, sel_impl};
+
+/// The native backend using Cocoa, the Accessibility APIs and SkyLight.
+pub struct MacBackend {
+    /// AX events are async and there are programs that eat events if they come to close to each
+    /// other (Firefox for example). We alternate which command we send first on every call, so if
+    /// the 2nd is eaten, we get it through on the next try.
+    order: axui::Order,
+}
+
+impl Default for MacBackend {
+    fn default() -> Self {
+        MacBackend {
+            order: axui::Order::MoveResize,
+        }
+    }
+}
+
+impl WindowBackend for MacBackend {
+    fn screens(&self) -> Result<Vec<Screen>> {
+        Ok(get_screens())
+    }
+
+    fn focused_window(&self) -> Result<WindowRef> {
+        match frontmost_application_pid() {
+            Some(pid) => Ok(WindowRef { pid, number: None }),
+            None => bail!("Frontmost application has no PID."),
+        }
+    }
+
+    fn window_list(&self, all_windows: bool) -> Result<Vec<Window>> {
+        Ok(axui::window_list(all_windows))
+    }
+
+    fn get_frame(&self, window: &WindowRef) -> Result<Rect> {
+        let element = axui::window_element(window.pid, window.number)?;
+        axui::window_frame(element.as_CFTypeRef() as id)
+    }
+
+    fn set_frame(&mut self, window: &WindowRef, frame: &Rect) -> Result<()> {
+        let element = axui::window_element(window.pid, window.number)?;
+        axui::move_window(element.as_CFTypeRef() as id, frame, self.order)?;
+        self.order = self.order.swap();
+        Ok(())
+    }
+
+    fn current_space_id(&self) -> Result<u64> {
+        Ok(skylight::get_current_space_id())
+    }
+}
+
+fn get_screens() -> Vec<Screen> {
+    let mut rv = Vec::new();
+    unsafe {
+        let screens: *mut Object = NSScreen::screens(nil);
+        for index in 0..NSArray::count(screens) {
+            let screen: *mut Object = msg_send![screens, objectAtIndex: index];
+            let visible_frame = screen.visibleFrame();
+            let frame = screen.frame();
+            let description: *mut Object = msg_send![screen, deviceDescription];
+            let number: *mut Object = msg_send![
+                description,
+                objectForKey: CFString::from_static_string("NSScreenNumber").as_concrete_TypeRef()
+            ];
+            let display_id: u32 = msg_send![number, unsignedIntValue];
+            // localizedName only exists since macOS 10.15.
+            let has_name: BOOL = msg_send![screen, respondsToSelector: sel!(localizedName)];
+            let name: *mut Object = if has_name == YES {
+                msg_send![screen, localizedName]
+            } else {
+                nil
+            };
+            let name = if name == nil {
+                String::new()
+            } else {
+                CFString::wrap_under_get_rule(name as CFStringRef).to_string()
+            };
+            rv.push(Screen {
+                index,
+                id: display_uuid(display_id),
+                name,
+                visible_frame: Rect {
+                    x: visible_frame.origin.x as i32,
+                    y: visible_frame.origin.y as i32,
+                    width: visible_frame.size.width as i32,
+                    height: visible_frame.size.height as i32,
+                },
+                frame: Rect {
+                    x: frame.origin.x as i32,
+                    y: frame.origin.y as i32,
+                    width: frame.size.width as i32,
+                    height: frame.size.height as i32,
+                },
+                scale_factor: screen.backingScaleFactor(),
+            })
+        }
+    };
+    // The screen frames have their origins in the bottom left of the screen, y going upwards.
+    // However, window bounds have the origin at the top left going down. We need to convert here
+    // to get them in the window space.
+    coords::screens_to_quartz(&mut rv);
+    rv
+}
+
+/// The UUID of the display, which stays the same when the display is reconnected. Falls back to
+/// the display id, which does not.
+fn display_uuid(display_id: u32) -> String {
+    unsafe {
+        let uuid = CGDisplayCreateUUIDFromDisplayID(display_id);
+        if uuid.is_null() {
+            return display_id.to_string();
+        }
+        let uuid = CFUUID::wrap_under_create_rule(uuid);
+        let s = CFUUIDCreateString(std::ptr::null(), uuid.as_concrete_TypeRef());
+        CFString::wrap_under_create_rule(s).to_string()
+    }
+}
+
+fn frontmost_application_pid() -> Option<i32> {
+    unsafe {
+        let workspace_class = Class::get("NSWorkspace").expect("always there.");
+        let wspace: *mut Object = msg_send![workspace_class, sharedWorkspace];
+        let front_app: *mut Object = msg_send![wspace, frontmostApplication];
+        if front_app == nil {
+            return None;
+        }
+        Some(msg_send![front_app, processIdentifier])
+    }
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn CGDisplayCreateUUIDFromDisplayID(display: u32) -> CFUUIDRef;
+}
@@ -1,30 +1,60 @@
 // TODO(hrapp): this is needed to shut up clippy warnings from `objc::msg_send`.
 #![allow(unexpected_cfgs)]
 
+use adjust::{Amount, Direction};
 use anyhow::{Context, Result, bail};
 use argh::FromArgs;
-use cocoa::appkit::NSScreen;
-use cocoa::base::nil;
-use cocoa::foundation::NSArray;
-use objc::runtime::Class;
-use objc::runtime::Object;
-use objc::{msg_send, sel, sel_impl};
+use backend::{Screen, Window, WindowBackend, WindowRef};
+use command::{AreaPlacement, MoveParameters, Placement, ScreenSelector};
+use config::Config;
+use error::Error;
+use history::History;
+use selector::WindowSelector;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs;
 use std::path::PathBuf;
 
+mod adjust;
+#[cfg(target_os = "macos")]
 mod axui;
+mod backend;
+mod command;
+mod config;
+mod coords;
+mod error;
+mod explain;
+mod fake;
+mod geometry;
+mod history;
+#[cfg(unix)]
+mod hyprland;
+#[cfg(target_os = "macos")]
+mod macos;
+mod selector;
+#[cfg(target_os = "macos")]
 mod skylight;
+#[cfg(unix)]
+mod sway;
+mod template;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
 
 /// Move windows or query spaces.
 #[derive(FromArgs)]
 struct Args {
+    /// the window system to talk to: macos, x11, sway (or i3), hyprland, fake:<fixture.json> or
+    /// fake-save:<fixture.json>.
+    /// Defaults to $MOVE_WINDOW_BACKEND, or else to the one detected from the environment.
+    #[argh(option)]
+    backend: Option<String>,
+
     #[argh(subcommand)]
     subcommand: Command,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -32,29 +62,6 @@ pub struct Rect {
     pub height: i32,
 }
 
-#[derive(Debug)]
-enum ScreenSelector {
-    Index(usize),
-    Char(char),
-}
-
-#[derive(Debug)]
-struct MoveParameters {
-    screen: ScreenSelector,
-    x_ratio: i32,
-    y_ratio: i32,
-    x_start: i32,
-    x_end: i32,
-    y_start: i32,
-    y_end: i32,
-}
-
-#[derive(Debug)]
-struct Screen {
-    index: u64,
-    visible_frame: Rect,
-    frame: Rect,
-}
 #[derive(FromArgs)]
 #[argh(subcommand)]
 enum Command {
@@ -62,6 +69,17 @@ enum Command {
     SetSpaceName(SetSpaceNameArgs),
     MoveWindow(MoveWindowArgs),
     Windows(WindowsArgs),
+    Screens(ScreensArgs),
+    Nudge(NudgeArgs),
+    Grow(GrowArgs),
+    Shrink(ShrinkArgs),
+    Expand(ExpandArgs),
+    Maximize(MaximizeArgs),
+    Undo(UndoArgs),
+    Redo(RedoArgs),
+    History(HistoryArgs),
+    Explain(ExplainArgs),
+    Describe(DescribeArgs),
 }
 
 /// Get the name of a space.
@@ -92,6 +110,20 @@ struct SetSpaceNameArgs {
 struct MoveWindowArgs {
     #[argh(positional)]
     move_command: String,
+
+    /// move this window instead of the focused one: a window number as printed by `windows`,
+    /// pid:<pid>, app:<name> or title:<regex>
+    #[argh(option)]
+    window: Option<WindowSelector>,
+
+    /// print parse errors as JSON on stdout instead of human readable on stderr
+    #[argh(switch)]
+    json: bool,
+
+    /// do not move any window, but print the frame computed against the displays described in
+    /// this JSON file
+    #[argh(option)]
+    screens_file: Option<PathBuf>,
 }
 
 /// List the currently open Windows.
@@ -99,10 +131,139 @@ struct MoveWindowArgs {
 #[argh(subcommand, name = "windows")]
 struct WindowsArgs {}
 
+/// Move the current top level Window without resizing it.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "nudge")]
+struct NudgeArgs {
+    #[argh(positional)]
+    /// left, right, up or down
+    direction: Direction,
+
+    #[argh(positional, default = "Amount::Pixels(10)")]
+    /// pixels (e.g. 20) or grid cells (e.g. 1/12 for one twelfth of the screen), defaults to 10
+    amount: Amount,
+}
+
+/// Move one edge of the current top level Window outwards.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "grow")]
+struct GrowArgs {
+    #[argh(positional)]
+    /// left, right, top or bottom
+    edge: Direction,
+
+    #[argh(positional, default = "Amount::Pixels(10)")]
+    /// pixels (e.g. 20) or grid cells (e.g. 1/12 for one twelfth of the screen), defaults to 10
+    amount: Amount,
+}
+
+/// Move one edge of the current top level Window inwards.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "shrink")]
+struct ShrinkArgs {
+    #[argh(positional)]
+    /// left, right, top or bottom
+    edge: Direction,
+
+    #[argh(positional, default = "Amount::Pixels(10)")]
+    /// pixels (e.g. 20) or grid cells (e.g. 1/12 for one twelfth of the screen), defaults to 10
+    amount: Amount,
+}
+
+/// Move one edge of the current top level Window outwards to the next grid line.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "expand")]
+struct ExpandArgs {
+    #[argh(positional)]
+    /// left, right, top or bottom
+    edge: Direction,
+
+    #[argh(positional, from_str_fn(adjust::parse_parts))]
+    /// number of parts of the grid, at most 1000
+    parts: i32,
+}
+
+/// Maximize the current top level Window, or restore it if it is maximized already.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "maximize")]
+struct MaximizeArgs {
+    #[argh(positional, default = "String::from(\"s\")")]
+    /// placement to toggle, defaults to `s`, the whole screen the Window is on
+    move_command: String,
+}
+
+/// Move the most recently moved Window back to where it was.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "undo")]
+struct UndoArgs {}
+
+/// Redo the most recently undone move.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "redo")]
+struct RedoArgs {}
+
+/// List the moves that can be undone and redone.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "history")]
+struct HistoryArgs {
+    /// print JSON instead of text
+    #[argh(switch)]
+    json: bool,
+}
+
+/// Describe a placement command in words and show the grid cells it covers, without moving any
+/// Window.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "explain")]
+struct ExplainArgs {
+    #[argh(positional)]
+    move_command: String,
+
+    /// compute the frame against the displays described in this JSON file instead of the
+    /// connected ones
+    #[argh(option)]
+    screens_file: Option<PathBuf>,
+}
+
+/// Print the grid command that comes closest to the frame of the current top level Window.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "describe")]
+struct DescribeArgs {
+    /// describe this frame, given as x,y,width,height, instead of the frame of the current top
+    /// level Window
+    #[argh(option, from_str_fn(parse_frame))]
+    frame: Option<Rect>,
+
+    /// print JSON instead of text
+    #[argh(switch)]
+    json: bool,
+
+    /// look for the frame on the displays described in this JSON file instead of the connected
+    /// ones. Needs --frame.
+    #[argh(option)]
+    screens_file: Option<PathBuf>,
+}
+
+/// List the screens and the selectors that pick them.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "screens")]
+struct ScreensArgs {
+    /// print JSON instead of a table
+    #[argh(switch)]
+    json: bool,
+
+    /// list the displays described in this JSON file instead of the connected ones
+    #[argh(option)]
+    screens_file: Option<PathBuf>,
+}
+
 #[derive(Serialize, Deserialize, Default)]
 struct State {
     /// mapping from space number to space name
     space_names: HashMap<u64, String>,
+    /// mapping from window key (see `window_key`) to the frame it had before it was maximized
+    #[serde(default)]
+    maximized: HashMap<String, Rect>,
 }
 
 impl State {
@@ -127,207 +288,396 @@ impl State {
     }
 }
 
-/// Constructs the path to the state file in a typical macOS location.
-fn state_file_path() -> Result<PathBuf> {
-    let home = std::env::var("HOME").context("Could not find HOME directory")?;
-    let mut path = PathBuf::from(home).join("Library/Application Support/move_window");
-    fs::create_dir_all(&path).context("Failed to create state directory")?;
-    path.push("state.json");
-    Ok(path)
+/// Constructs the path to the directory of the config file, creating it if needed.
+fn config_dir() -> Result<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
 }
 
-fn next_integer(a: &mut ::std::iter::Peekable<impl Iterator<Item = char>>) -> Result<i32, String> {
-    let c = a.next().ok_or_else(|| "No more items".to_string())?;
-    let v = c
-        .to_digit(10)
-        .ok_or_else(|| "Next not an integer.".to_string())?;
-    Ok(v as i32)
+/// Constructs the path to the directory of the state and history files, creating it if needed.
+fn state_dir() -> Result<PathBuf> {
+    app_dir("XDG_STATE_HOME", ".local/state")
 }
 
-impl MoveParameters {
-    pub fn from_command(s: &str) -> Result<Self, String> {
-        let mut i = s.chars().peekable();
+/// Constructs the path to our directory in the typical macOS location. Elsewhere, it is in the
+/// XDG base directory named by `$xdg_var`, which defaults to `fallback` in the home directory.
+fn app_dir(xdg_var: &str, fallback: &str) -> Result<PathBuf> {
+    let home = || -> Result<PathBuf> {
+        let home = std::env::var_os("HOME").context("Could not find HOME directory")?;
+        Ok(PathBuf::from(home))
+    };
+    let base = if cfg!(target_os = "macos") {
+        home()?.join("Library/Application Support")
+    } else {
+        // Relative paths are invalid in XDG variables and to be ignored.
+        match std::env::var_os(xdg_var)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+        {
+            Some(dir) => dir,
+            None => home()?.join(fallback),
+        }
+    };
+    let path = base.join("move_window");
+    fs::create_dir_all(&path)
+        .with_context(|| format!("Failed to create directory {}", path.display()))?;
+    Ok(path)
+}
 
-        let screen = {
-            let c = i.next().ok_or_else(|| "No more items".to_string())?;
-            match c {
-                '0'..='9' => ScreenSelector::Index(c.to_digit(10).expect("by design.") as usize),
-                c => ScreenSelector::Char(c),
-            }
-        };
+/// Constructs the path to the state file.
+fn state_file_path() -> Result<PathBuf> {
+    Ok(state_dir()?.join("state.json"))
+}
 
-        let mut params = MoveParameters {
-            screen,
-            x_ratio: 1,
-            y_ratio: 1,
-            x_start: 0,
-            x_end: 0,
-            y_start: 0,
-            y_end: 0,
-        };
+/// Computes the frame that `placement` describes on `screens`. `window` is the current frame of
+/// the window to move, if there is one.
+fn compute_frame(
+    screens: &[Screen],
+    placement: &Placement,
+    config: &Config,
+    window: Option<&Rect>,
+) -> Result<Rect> {
+    let screen = placement
+        .screen()
+        .select(screens, window, &config.aliases)?;
+    let area = config.tiling_area(screen);
+    Ok(match placement {
+        Placement::Grid(params) => geometry::compute_frame(&area, params, config.gap),
+        Placement::Sized(sized) => geometry::sized_frame(&area, sized),
+        Placement::Area(placement) => {
+            let params = area_parameters(placement, screen, config)?;
+            geometry::compute_frame(&area, &params, config.gap)
+        }
+    })
+}
 
-        params.x_ratio = match i.peek() {
-            Some(_) => next_integer(&mut i)?,
-            None => return Ok(params),
-        };
-        params.x_start = match i.peek() {
-            Some(_) => next_integer(&mut i)?,
-            None => return Ok(params),
-        };
-        params.x_end = params.x_start;
-        params.x_end = match i.peek() {
-            Some(&'-') => {
-                i.next();
-                next_integer(&mut i)?
-            }
-            Some(_) => params.x_end,
-            None => return Ok(params),
-        };
+/// Looks up the grid cells of the area `placement` names in the template of `screen`.
+fn area_parameters(
+    placement: &AreaPlacement,
+    screen: &Screen,
+    config: &Config,
+) -> Result<MoveParameters> {
+    let template = config.template(screen)?;
+    let params = template
+        .as_ref()
+        .and_then(|t| t.area(placement.screen.clone(), &placement.name))
+        .ok_or_else(|| Error::UnknownArea {
+            area: placement.name.clone(),
+            screen: screen.index,
+            areas: template
+                .iter()
+                .flat_map(|t| t.names())
+                .map(str::to_string)
+                .collect(),
+        })?;
+    Ok(params)
+}
 
-        params.y_ratio = match i.peek() {
-            Some(_) => next_integer(&mut i)?,
-            None => return Ok(params),
-        };
-        params.y_start = match i.peek() {
-            Some(_) => next_integer(&mut i)?,
-            None => return Ok(params),
-        };
-        params.y_end = params.y_start;
-        params.y_end = match i.peek() {
-            Some(&'-') => {
-                i.next();
-                next_integer(&mut i)?
-            }
-            Some(_) => return Err("No more input expected.".to_string()),
-            None => return Ok(params),
-        };
-        Ok(params)
-    }
-}
-
-fn get_screens() -> Vec<Screen> {
-    let mut rv = Vec::new();
-    unsafe {
-        let screens: *mut Object = NSScreen::screens(nil);
-        for index in 0..NSArray::count(screens) {
-            let screen: *mut Object = msg_send![screens, objectAtIndex: index];
-            let visible_frame = screen.visibleFrame();
-            let frame = screen.frame();
-            rv.push(Screen {
-                index,
-                visible_frame: Rect {
-                    x: visible_frame.origin.x as i32,
-                    y: visible_frame.origin.y as i32,
-                    width: visible_frame.size.width as i32,
-                    height: visible_frame.size.height as i32,
-                },
-                frame: Rect {
-                    x: frame.origin.x as i32,
-                    y: frame.origin.y as i32,
-                    width: frame.size.width as i32,
-                    height: frame.size.height as i32,
-                },
-            })
+fn explain(backend: &mut LazyBackend, args: ExplainArgs) -> Result<()> {
+    let placement = Placement::from_command(&args.move_command).map_err(Error::Parse)?;
+    println!("{}", explain::describe(&placement));
+
+    let config = Config::load()?;
+    let screens = match &args.screens_file {
+        Some(path) => backend::read_screens_file(path)?,
+        None => backend.get()?.screens()?,
+    };
+    let screen = placement.screen().select(&screens, None, &config.aliases)?;
+    let grid = match &placement {
+        Placement::Grid(params) => Some(params.clone()),
+        Placement::Sized(_) => None,
+        Placement::Area(area) => {
+            let params = area_parameters(area, screen, &config)?;
+            println!("which is {}", explain::describe_grid(&params));
+            Some(params)
         }
     };
-    // The window frames have their origins in the bottom left of the screen, y going upwards.
-    // However, screen bounds have the origin at the top left going down. We need to convert here
-    // to get them in the screen space.
-    for idx in 1..rv.len() {
-        let y = rv[0].frame.height - rv[idx].visible_frame.height - rv[idx].visible_frame.y;
-        rv[idx].visible_frame.y = y;
-    }
-    rv
-}
-
-fn frontmost_application_pid() -> Option<i32> {
-    unsafe {
-        let workspace_class = Class::get("NSWorkspace").expect("always there.");
-        let wspace: *mut Object = msg_send![workspace_class, sharedWorkspace];
-        let front_app: *mut Object = msg_send![wspace, frontmostApplication];
-        if front_app == nil {
-            return None;
+    let frame = compute_frame(&screens, &placement, &config, None)?;
+    println!(
+        "frame: {}x{} at ({}, {})",
+        frame.width, frame.height, frame.x, frame.y
+    );
+    if let Some(params) = grid {
+        match explain::render_grid(&params) {
+            Some(drawing) => print!("{}", drawing),
+            None => println!(
+                "(grids with more than {} parts are not drawn)",
+                explain::MAX_DRAWN_PARTS
+            ),
         }
-        Some(msg_send![front_app, processIdentifier])
     }
+    Ok(())
 }
 
-fn move_window(args: MoveWindowArgs) -> Result<()> {
-    let params = match MoveParameters::from_command(&args.move_command) {
+fn move_window(backend: &mut LazyBackend, args: MoveWindowArgs) -> Result<()> {
+    let placement = match Placement::from_command(&args.move_command) {
         Ok(v) => v,
-        Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
+        Err(e) if args.json => {
+            println!("{}", e.to_json());
+            std::process::exit(Error::Parse(e).exit_code());
         }
+        Err(e) => return Err(Error::Parse(e).into()),
     };
+    let config = Config::load()?;
 
-    let screens = get_screens();
-    let get_screen_by_index = |index| {
-        let mut screen = None;
-        for s in &screens {
-            if s.index == index as u64 {
-                screen = Some(s)
-            }
-        }
-        screen.expect("Unknown screen index.")
+    if let Some(path) = &args.screens_file {
+        let screens = backend::read_screens_file(path)?;
+        let frame = compute_frame(&screens, &placement, &config, None)?;
+        println!("{}", serde_json::to_string(&frame)?);
+        return Ok(());
+    }
+
+    let backend = backend.get()?;
+    let window = match &args.window {
+        Some(selector) => select_window(backend, selector)?,
+        None => backend.focused_window()?,
     };
-    let screen = match params.screen {
-        ScreenSelector::Index(index) => get_screen_by_index(index),
-        ScreenSelector::Char(c) => match c {
-            'm' | 'c' => get_screen_by_index(0),
-            'l' => screens
-                .iter()
-                .min_by_key(|s| s.frame.x)
-                .expect("there are always screens."),
-            'r' => screens
-                .iter()
-                .max_by_key(|s| s.frame.x)
-                .expect("there are always screens."),
-            't' | 'u' => screens
-                .iter()
-                .max_by_key(|s| s.frame.y)
-                .expect("there are always screens."),
-            'b' | 'd' => screens
-                .iter()
-                .min_by_key(|s| s.frame.y)
-                .expect("there are always screens."),
-            _ => panic!("Unknown character for screen selection: {}", c),
-        },
+    let current = backend.get_frame(&window)?;
+    let screens = backend.screens()?;
+    let frame = match cycle_frame(&screens, &placement, &config, &current)? {
+        Some(frame) => frame,
+        None => compute_frame(&screens, &placement, &config, Some(&current))?,
     };
+    move_recorded(backend, &window, &current, &frame)
+}
 
-    let width = f64::from(screen.visible_frame.width) / f64::from(params.x_ratio);
-    let height = f64::from(screen.visible_frame.height) / f64::from(params.y_ratio);
-    let frame = Rect {
-        x: (f64::from(screen.visible_frame.x) + width * f64::from(params.x_start)).round() as i32,
-        y: (f64::from(screen.visible_frame.y) + height * f64::from(params.y_start)).round() as i32,
-        width: (width * f64::from(params.x_end - params.x_start + 1)).round() as i32,
-        height: (height * f64::from(params.y_end - params.y_start + 1)).round() as i32,
-    };
+/// Returns the window that `selector` picks from all open windows.
+fn select_window(backend: &dyn WindowBackend, selector: &WindowSelector) -> Result<WindowRef> {
+    match selector.select(&backend.window_list(true)?) {
+        Some(w) => Ok(WindowRef {
+            pid: w.owner_pid,
+            number: Some(w.number),
+        }),
+        None => Err(Error::NoMatchingWindow(selector.to_string()).into()),
+    }
+}
 
-    let pid = match frontmost_application_pid() {
-        Some(p) => p,
-        None => bail!("Frontmost application has no PID."),
+/// If `placement` is part of a cycle and the window at `current` already sits in one of the cycle's
+/// frames, returns the frame of the step after that one.
+fn cycle_frame(
+    screens: &[Screen],
+    placement: &Placement,
+    config: &Config,
+    current: &Rect,
+) -> Result<Option<Rect>> {
+    let Some(steps) = config.cycle_containing(placement) else {
+        return Ok(None);
     };
+    let frames = steps
+        .iter()
+        .map(|step| compute_frame(screens, step, config, Some(current)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(frames
+        .iter()
+        .position(|f| f.approx_eq(current, config.tolerance))
+        .map(|i| frames[(i + 1) % frames.len()].clone()))
+}
+
+/// Moves `window` to `frame`.
+fn place_window(backend: &mut dyn WindowBackend, window: &WindowRef, frame: &Rect) -> Result<()> {
     // We try really hard to move the windo into the right position, but give up
     // after 10 times if it does not work.
-    // Also, AX events are async and there are programs that eat events if they come to close to
-    // each other (Firefox for example). This is an attempt to work around this; we alternate which
-    // command we send first on every try, so if the 2nd is eaten, we get it through on the next
-    // try.
-    let mut order = axui::Order::MoveResize;
     for _ in 0..10 {
-        axui::move_frontmost_window(pid, &frame, order)?;
-        order = order.swap();
-        if axui::get_window_position_and_size(pid)? == frame {
+        backend.set_frame(window, frame)?;
+        if backend.get_frame(window)? == *frame {
             break;
         }
     }
     Ok(())
 }
 
-fn set_space_name(args: SetSpaceNameArgs) -> Result<()> {
+/// Changes the frame of the focused window by applying `adjust` to its current frame and the
+/// tiling area of the screen it is on.
+fn adjust_window(
+    backend: &mut dyn WindowBackend,
+    adjust: impl FnOnce(&Rect, &Rect, &Config) -> Rect,
+) -> Result<()> {
+    let config = Config::load()?;
+    let window = backend.focused_window()?;
+    let current = backend.get_frame(&window)?;
+    let screens = backend.screens()?;
+    let screen = ScreenSelector::Char('s').select(&screens, Some(&current), &config.aliases)?;
+    let frame = adjust(&current, &config.tiling_area(screen), &config);
+    move_recorded(backend, &window, &current, &frame)
+}
+
+/// Moves `window` from `current` to `frame`, recording `current` in the history first so that the
+/// move can be undone. Moves that do not change anything are not recorded.
+fn move_recorded(
+    backend: &mut dyn WindowBackend,
+    window: &WindowRef,
+    current: &Rect,
+    frame: &Rect,
+) -> Result<()> {
+    if current != frame {
+        let mut history = History::load()?;
+        history.record(history_entry(backend, window, current)?);
+        history.save()?;
+    }
+    place_window(backend, window, frame)
+}
+
+/// Returns `window`, which currently is at `frame`, with its window number filled in, together
+/// with its entry in the window list. If the backend did not say which window it is, we look for a
+/// window of the same process with the same frame, give or take a pixel of rounding, so that we
+/// can refer to the same window in later invocations.
+fn identify_window(
+    backend: &dyn WindowBackend,
+    window: &WindowRef,
+    frame: &Rect,
+) -> Result<(WindowRef, Option<Window>)> {
+    let windows = backend.window_list(true)?;
+    let found = windows.into_iter().find(|w| {
+        w.owner_pid == window.pid
+            && match window.number {
+                Some(n) => w.number == n,
+                None => w.bounds.approx_eq(frame, 1),
+            }
+    });
+    let window = WindowRef {
+        pid: window.pid,
+        number: found.as_ref().map(|w| w.number).or(window.number),
+    };
+    Ok((window, found))
+}
+
+/// Describes `window`, which currently is at `frame`, for the history.
+fn history_entry(
+    backend: &dyn WindowBackend,
+    window: &WindowRef,
+    frame: &Rect,
+) -> Result<history::Entry> {
+    let (window, found) = identify_window(backend, window, frame)?;
+    Ok(match found {
+        Some(w) => history::Entry::new(window, w.owner_name, w.name, frame.clone()),
+        None => history::Entry::new(window, String::new(), None, frame.clone()),
+    })
+}
+
+/// A key identifying a window across invocations.
+fn window_key(window: &WindowRef) -> String {
+    match window.number {
+        Some(n) => format!("{}:{}", window.pid, n),
+        None => window.pid.to_string(),
+    }
+}
+
+fn maximize(backend: &mut LazyBackend, args: MaximizeArgs) -> Result<()> {
+    let placement = Placement::from_command(&args.move_command).map_err(Error::Parse)?;
+    let config = Config::load()?;
+    let backend = backend.get()?;
+    let window = backend.focused_window()?;
+    let current = backend.get_frame(&window)?;
+    let maximized = compute_frame(&backend.screens()?, &placement, &config, Some(&current))?;
+    let (window, _) = identify_window(backend, &window, &current)?;
+    let key = window_key(&window);
+
+    let mut state = State::load()?;
+    // Forget about windows that do not exist anymore. Windows without a number are only known by
+    // their process, so they stay as long as it has windows.
+    let keys = backend
+        .window_list(true)?
+        .iter()
+        .flat_map(|w| {
+            [Some(w.number), None].map(|number| {
+                window_key(&WindowRef {
+                    pid: w.owner_pid,
+                    number,
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    state.maximized.retain(|k, _| *k == key || keys.contains(k));
+
+    let frame = if current.approx_eq(&maximized, config.tolerance) {
+        match state.maximized.remove(&key) {
+            Some(frame) => frame,
+            None => bail!("The window is maximized, but there is no frame to restore."),
+        }
+    } else {
+        state.maximized.insert(key, current.clone());
+        maximized
+    };
+    state.save()?;
+    move_recorded(backend, &window, &current, &frame)
+}
+
+fn undo(backend: &mut dyn WindowBackend, _args: UndoArgs) -> Result<()> {
+    let mut history = History::load()?;
+    let entry = history.undo(|e| current_frame(backend, e));
+    // Entries of closed windows are dropped even if the move fails.
+    history.save()?;
+    let Some(entry) = entry? else {
+        bail!("Nothing to undo.");
+    };
+    place_window(backend, &entry.window, &entry.frame)
+}
+
+fn redo(backend: &mut dyn WindowBackend, _args: RedoArgs) -> Result<()> {
+    let mut history = History::load()?;
+    let entry = history.redo(|e| current_frame(backend, e));
+    history.save()?;
+    let Some(entry) = entry? else {
+        bail!("Nothing to redo.");
+    };
+    place_window(backend, &entry.window, &entry.frame)
+}
+
+/// The current frame of the window of `entry`, or `None` if the window was closed.
+fn current_frame(backend: &dyn WindowBackend, entry: &history::Entry) -> Result<Option<Rect>> {
+    let window = &entry.window;
+    if let Some(number) = window.number {
+        let open = backend
+            .window_list(true)?
+            .iter()
+            .any(|w| w.owner_pid == window.pid && w.number == number);
+        if !open {
+            return Ok(None);
+        }
+    }
+    backend.get_frame(window).map(Some)
+}
+
+fn show_history(args: HistoryArgs) -> Result<()> {
+    let history = History::load()?;
+    if args.json {
+        println!("{}", serde_json::to_string(&history)?);
+        return Ok(());
+    }
+
+    let now = history::now();
+    let print = |title: &str, entries: &[history::Entry]| {
+        println!("{}:", title);
+        for (i, e) in entries.iter().rev().enumerate() {
+            let age = now.saturating_sub(e.time);
+            let age = match age {
+                0..60 => format!("{}s", age),
+                60..3600 => format!("{}m", age / 60),
+                _ => format!("{}h", age / 3600),
+            };
+            println!(
+                "  {:>2}  {} {:?}  {}x{} at ({}, {})  {} ago",
+                i + 1,
+                e.owner_name,
+                e.title.as_deref().unwrap_or(""),
+                e.frame.width,
+                e.frame.height,
+                e.frame.x,
+                e.frame.y,
+                age
+            );
+        }
+    };
+    print("undo", &history.undo);
+    print("redo", &history.redo);
+    Ok(())
+}
+
+fn set_space_name(backend: &dyn WindowBackend, args: SetSpaceNameArgs) -> Result<()> {
     let mut state = State::load()?;
-    let id = args.id.unwrap_or_else(skylight::get_current_space_id);
+    let id = match args.id {
+        Some(id) => id,
+        None => backend.current_space_id()?,
+    };
     match args.name {
         None => {
             state.space_names.remove(&id);
@@ -341,36 +691,706 @@ fn set_space_name(args: SetSpaceNameArgs) -> Result<()> {
     Ok(())
 }
 
-fn get_space_name(args: GetSpaceNameArgs) -> Result<()> {
+fn get_space_name(backend: &dyn WindowBackend, args: GetSpaceNameArgs) -> Result<()> {
+    println!("{}", space_name(backend, args.id)?);
+    Ok(())
+}
+
+/// The name of the space with `id`, or of the current space: the one set with `set_space_name`,
+/// or else the one the window system gives it.
+fn space_name(backend: &dyn WindowBackend, id: Option<u64>) -> Result<String> {
     let state = State::load()?;
-    let id = args.id.unwrap_or_else(skylight::get_current_space_id);
-    let name = match state.space_names.get(&id) {
-        Some(s) => s as &str,
-        None => "Unnamed",
+    let id = match id {
+        Some(id) => id,
+        None => backend.current_space_id()?,
     };
-    println!("{name}");
-    Ok(())
+    Ok(match state.space_names.get(&id) {
+        Some(s) => s.clone(),
+        None => backend
+            .space_name(id)?
+            .unwrap_or_else(|| "Unnamed".to_string()),
+    })
 }
 
-fn windows(_args: WindowsArgs) -> Result<()> {
-    let out = axui::window_list(true);
+fn windows(backend: &dyn WindowBackend, _args: WindowsArgs) -> Result<()> {
+    let out = backend.window_list(true)?;
     let o = serde_json::to_string(&out).unwrap();
     println!("{o}");
     Ok(())
 }
 
-fn main() -> Result<()> {
-    let args: Args = argh::from_env();
-    if !axui::check_accessibility_permission() {
-        panic!("Accessibility permissions not granted. Please enable them in System Settings.");
+/// Parses a frame given as `x,y,width,height`.
+fn parse_frame(value: &str) -> Result<Rect, String> {
+    let fields = value
+        .split(',')
+        .map(|v| v.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid frame {:?}: {}", value, e))?;
+    match fields[..] {
+        [x, y, width, height] => Ok(Rect {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => Err(format!(
+            "invalid frame {:?}: expected x,y,width,height",
+            value
+        )),
     }
+}
+
+/// The grid command that `describe` found for a frame.
+#[derive(Serialize)]
+struct Description {
+    /// the command in the compact form if possible, otherwise in the long form
+    command: String,
+    long_command: String,
+    frame: Rect,
+    /// the largest distance in pixels between an edge of `frame` and the described frame
+    error: i32,
+    /// whether `error` is within the configured tolerance
+    matches: bool,
+}
+
+fn describe(backend: &mut LazyBackend, args: DescribeArgs) -> Result<()> {
+    let config = Config::load()?;
+    let (screens, frame) = match (&args.screens_file, args.frame) {
+        (Some(path), Some(frame)) => (backend::read_screens_file(path)?, frame),
+        (Some(_), None) => bail!("--screens-file needs --frame."),
+        (None, frame) => {
+            let backend = backend.get()?;
+            let frame = match frame {
+                Some(frame) => frame,
+                None => backend.get_frame(&backend.focused_window()?)?,
+            };
+            (backend.screens()?, frame)
+        }
+    };
+    let screen = ScreenSelector::Char('s').select(&screens, Some(&frame), &config.aliases)?;
+    let found = geometry::nearest_grid(
+        &config.tiling_area(screen),
+        &frame,
+        ScreenSelector::Index(screen.index as usize),
+        config.gap,
+        config.tolerance,
+    );
+    let description = Description {
+        command: found
+            .params
+            .to_compact()
+            .unwrap_or_else(|| found.params.to_string()),
+        long_command: found.params.to_string(),
+        frame: compute_frame(
+            &screens,
+            &Placement::Grid(found.params.clone()),
+            &config,
+            None,
+        )?,
+        error: found.error,
+        matches: found.error <= config.tolerance,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string(&description)?);
+    } else if description.matches {
+        println!("{}", description.command);
+    } else {
+        println!(
+            "{} (nearest, off by {} pixels)",
+            description.command, description.error
+        );
+    }
+    Ok(())
+}
+
+/// A screen together with the selectors and aliases resolving to it.
+#[derive(Serialize)]
+struct ScreenInfo {
+    #[serde(flatten)]
+    screen: Screen,
+    selectors: Vec<String>,
+}
+
+fn screens(backend: &mut LazyBackend, args: ScreensArgs) -> Result<()> {
+    let config = Config::load()?;
+    let screens = match &args.screens_file {
+        Some(path) => backend::read_screens_file(path)?,
+        None => backend.get()?.screens()?,
+    };
+
+    let mut aliases = config.aliases.keys().collect::<Vec<_>>();
+    aliases.sort();
+    let selectors = ['m', 'l', 'r', 't', 'b']
+        .into_iter()
+        .map(ScreenSelector::Char)
+        .chain(aliases.into_iter().cloned().map(ScreenSelector::Alias))
+        .collect::<Vec<_>>();
+    let infos = screens
+        .iter()
+        .map(|screen| ScreenInfo {
+            screen: screen.clone(),
+            selectors: selectors
+                .iter()
+                .filter(|sel| {
+                    sel.select(&screens, None, &config.aliases)
+                        .is_ok_and(|s| s.index == screen.index)
+                })
+                .map(|sel| sel.to_string())
+                .collect(),
+        })
+        .collect::<Vec<_>>();
 
+    if args.json {
+        println!("{}", serde_json::to_string(&infos)?);
+        return Ok(());
+    }
+
+    let rect = |r: &Rect| format!("{}x{} at ({}, {})", r.width, r.height, r.x, r.y);
+    let mut rows = vec![
+        [
+            "index",
+            "name",
+            "id",
+            "frame",
+            "visible frame",
+            "scale",
+            "selectors",
+        ]
+        .map(String::from),
+    ];
+    for info in &infos {
+        rows.push([
+            info.screen.index.to_string(),
+            info.screen.name.clone(),
+            info.screen.id.clone(),
+            rect(&info.screen.frame),
+            rect(&info.screen.visible_frame),
+            info.screen.scale_factor.to_string(),
+            info.selectors.join(" "),
+        ]);
+    }
+    let widths = (0..rows[0].len())
+        .map(|col| {
+            rows.iter()
+                .map(|r| r[col].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// The backends `--backend` and `MOVE_WINDOW_BACKEND` accept.
+const BACKENDS: &str =
+    "macos, x11, sway (or i3), hyprland, fake:<fixture.json>, fake-save:<fixture.json> or auto";
+
+/// The window system, connected to on first use, so that commands working on a screens file run
+/// without one.
+struct LazyBackend {
+    /// The backend asked for with `--backend`, see `create_backend`.
+    spec: Option<String>,
+    backend: Option<Box<dyn WindowBackend>>,
+}
+
+impl LazyBackend {
+    fn get(&mut self) -> Result<&mut dyn WindowBackend> {
+        if self.backend.is_none() {
+            self.backend = Some(create_backend(self.spec.as_deref())?);
+        }
+        Ok(self.backend.as_deref_mut().expect("just connected."))
+    }
+}
+
+/// Creates the backend `spec` asks for, or else the one `MOVE_WINDOW_BACKEND` asks for, or else
+/// the one detected from the environment, see `detect_backend`.
+fn create_backend(spec: Option<&str>) -> Result<Box<dyn WindowBackend>> {
+    let from_env = std::env::var("MOVE_WINDOW_BACKEND").ok();
+    let spec = match spec.or(from_env.as_deref()).filter(|spec| *spec != "auto") {
+        Some(spec) => spec,
+        None => detect_backend(|name| std::env::var_os(name))?,
+    };
+    if let Some(path) = spec.strip_prefix("fake:") {
+        return Ok(Box::new(fake::FakeBackend::load(path, false)?));
+    }
+    if let Some(path) = spec.strip_prefix("fake-save:") {
+        return Ok(Box::new(fake::FakeBackend::load(path, true)?));
+    }
+    Ok(match spec {
+        #[cfg(target_os = "macos")]
+        "macos" => {
+            if !axui::check_accessibility_permission() {
+                return Err(Error::AccessibilityNotGranted.into());
+            }
+            Box::new(macos::MacBackend::default())
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        "x11" => Box::new(x11::X11Backend::connect()?),
+        #[cfg(unix)]
+        "sway" | "i3" => Box::new(sway::SwayBackend::connect()?),
+        #[cfg(unix)]
+        "hyprland" => Box::new(hyprland::HyprlandBackend::connect()?),
+        _ => bail!(
+            "Unknown or unsupported backend {:?}. Use one of {}.",
+            spec,
+            BACKENDS
+        ),
+    })
+}
+
+/// Picks the backend for the window system we run in. Hyprland and sway also set `$DISPLAY` for
+/// their X11 clients, so they are checked first. `var` looks up an environment variable.
+fn detect_backend(var: impl Fn(&str) -> Option<OsString>) -> Result<&'static str> {
+    if cfg!(target_os = "macos") {
+        return Ok("macos");
+    }
+    let is_set = |name: &str| var(name).is_some_and(|v| !v.is_empty());
+    if is_set("HYPRLAND_INSTANCE_SIGNATURE") {
+        Ok("hyprland")
+    } else if is_set("SWAYSOCK") || is_set("I3SOCK") {
+        Ok("sway")
+    } else if is_set("DISPLAY") {
+        Ok("x11")
+    } else {
+        bail!(
+            "Could not detect the window system. Pick a backend with --backend or \
+             MOVE_WINDOW_BACKEND: {}.",
+            BACKENDS
+        )
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{:#}", e);
+        let code = match e.downcast_ref::<Error>() {
+            Some(e) => e.exit_code(),
+            None => 1,
+        };
+        std::process::exit(code);
+    }
+}
+
+fn run() -> Result<()> {
+    let args: Args = argh::from_env();
+    let mut backend = LazyBackend {
+        spec: args.backend,
+        backend: None,
+    };
     match args.subcommand {
-        Command::MoveWindow(args) => move_window(args)?,
-        Command::GetSpaceName(args) => get_space_name(args)?,
-        Command::SetSpaceName(args) => set_space_name(args)?,
-        Command::Windows(args) => windows(args)?,
+        Command::MoveWindow(args) => move_window(&mut backend, args)?,
+        Command::GetSpaceName(args) => get_space_name(backend.get()?, args)?,
+        Command::SetSpaceName(args) => set_space_name(backend.get()?, args)?,
+        Command::Windows(args) => windows(backend.get()?, args)?,
+        Command::Screens(args) => screens(&mut backend, args)?,
+        Command::Nudge(args) => adjust_window(backend.get()?, |frame, area, _| {
+            adjust::nudge(frame, area, args.direction, args.amount)
+        })?,
+        Command::Grow(args) => adjust_window(backend.get()?, |frame, area, _| {
+            adjust::grow(frame, area, args.edge, args.amount)
+        })?,
+        Command::Shrink(args) => adjust_window(backend.get()?, |frame, area, _| {
+            adjust::shrink(frame, area, args.edge, args.amount)
+        })?,
+        Command::Maximize(args) => maximize(&mut backend, args)?,
+        Command::Undo(args) => undo(backend.get()?, args)?,
+        Command::Redo(args) => redo(backend.get()?, args)?,
+        Command::History(args) => show_history(args)?,
+        Command::Explain(args) => explain(&mut backend, args)?,
+        Command::Describe(args) => describe(&mut backend, args)?,
+        Command::Expand(args) => adjust_window(backend.get()?, |frame, area, config| {
+            adjust::expand(frame, area, args.edge, args.parts, config.gap)
+        })?,
     };
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{FakeBackend, FakeDesktop};
+    use std::sync::{Mutex, PoisonError};
+
+    /// Runs `f` with fresh, empty config and state directories. They are found through the
+    /// environment, so tests using them take turns.
+    fn with_data_dir(f: impl FnOnce()) {
+        static LOCK: Mutex<()> = Mutex::new(());
+        let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let home = tempfile::tempdir().unwrap();
+        // SAFETY: all tests reading the environment hold `LOCK`.
+        unsafe {
+            std::env::set_var("HOME", home.path());
+            std::env::remove_var("XDG_CONFIG_HOME");
+            std::env::remove_var("XDG_STATE_HOME");
+        }
+        f();
+    }
+
+    /// One 1920x1080 screen with a menu bar, a focused terminal and a browser behind it.
+    fn desktop() -> FakeDesktop {
+        serde_json::from_str(include_str!("../tests/fixtures/desktop.json")).unwrap()
+    }
+
+    fn move_args(move_command: &str, window: Option<&str>) -> MoveWindowArgs {
+        MoveWindowArgs {
+            move_command: move_command.to_string(),
+            json: false,
+            screens_file: None,
+            window: window.map(|w| w.parse().unwrap()),
+        }
+    }
+
+    fn fake_backend() -> LazyBackend {
+        LazyBackend {
+            spec: None,
+            backend: Some(Box::new(FakeBackend::new(desktop()))),
+        }
+    }
+
+    fn frame_of(backend: &mut LazyBackend, pid: i32, number: u32) -> Rect {
+        let window = WindowRef {
+            pid,
+            number: Some(number),
+        };
+        backend.get().unwrap().get_frame(&window).unwrap()
+    }
+
+    const RIGHT_HALF: Rect = Rect {
+        x: 960,
+        y: 25,
+        width: 960,
+        height: 1055,
+    };
+
+    #[test]
+    fn move_window_moves_the_focused_window() {
+        with_data_dir(|| {
+            let mut backend = fake_backend();
+            move_window(&mut backend, move_args("021", None)).unwrap();
+            assert_eq!(frame_of(&mut backend, 10, 1), RIGHT_HALF);
+            assert_eq!(frame_of(&mut backend, 20, 2).x, 200);
+        });
+    }
+
+    #[test]
+    fn move_window_moves_the_selected_window() {
+        with_data_dir(|| {
+            let mut backend = fake_backend();
+            move_window(&mut backend, move_args("021", Some("app:safari"))).unwrap();
+            assert_eq!(frame_of(&mut backend, 20, 2), RIGHT_HALF);
+            assert_eq!(frame_of(&mut backend, 10, 1).x, 100);
+        });
+    }
+
+    #[test]
+    fn move_window_fails_if_no_window_matches() {
+        with_data_dir(|| {
+            let err = move_window(&mut fake_backend(), move_args("021", Some("title:^Drafts")))
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::NoMatchingWindow(_))
+            ));
+        });
+    }
+
+    fn write_config(json: &str) -> Config {
+        fs::write(config_dir().unwrap().join("config.json"), json).unwrap();
+        Config::load().unwrap()
+    }
+
+    #[test]
+    fn repeating_a_cycle_command_advances_and_wraps() {
+        with_data_dir(|| {
+            let config = write_config(r#"{"cycles": [["021", "0:3:2", "0:3:1-2"]]}"#);
+            let screens = desktop().screens;
+            let next = |command, current: &Rect, config: &Config| {
+                let placement = Placement::from_command(command).unwrap();
+                cycle_frame(&screens, &placement, config, current).unwrap()
+            };
+            let third = Rect {
+                x: 1280,
+                width: 640,
+                ..RIGHT_HALF
+            };
+            let two_thirds = Rect {
+                x: 640,
+                width: 1280,
+                ..RIGHT_HALF
+            };
+            assert_eq!(next("021", &RIGHT_HALF, &config), Some(third.clone()));
+            assert_eq!(next("021", &third, &config), Some(two_thirds.clone()));
+            assert_eq!(next("021", &two_thirds, &config), Some(RIGHT_HALF));
+            // Any command of the cycle continues it where the window is.
+            assert_eq!(next("0:3:1-2", &RIGHT_HALF, &config), Some(third.clone()));
+            // Windows that are not in any of the cycle's frames go to the command's own frame.
+            let elsewhere = frame_of(&mut fake_backend(), 10, 1);
+            assert_eq!(next("021", &elsewhere, &config), None);
+            // Commands outside of any cycle do not cycle.
+            assert_eq!(next("020", &RIGHT_HALF, &config), None);
+
+            // Edges may be off by up to `tolerance` pixels.
+            let off_by_5 = Rect {
+                x: 965,
+                width: 950,
+                ..RIGHT_HALF
+            };
+            let off_by_15 = Rect {
+                x: 975,
+                width: 940,
+                ..RIGHT_HALF
+            };
+            assert_eq!(next("021", &off_by_5, &config), Some(third.clone()));
+            assert_eq!(next("021", &off_by_15, &config), None);
+            let lenient = write_config(r#"{"cycles": [["021", "0:3:2"]], "tolerance": 20}"#);
+            assert_eq!(next("021", &off_by_15, &lenient), Some(third));
+        });
+    }
+
+    #[test]
+    fn invalid_cycles_fail_when_the_config_is_loaded() {
+        with_data_dir(|| {
+            fs::write(
+                config_dir().unwrap().join("config.json"),
+                r#"{"cycles": [["021", "0:3:x"]]}"#,
+            )
+            .unwrap();
+            let err = Config::load().unwrap_err();
+            assert!(
+                format!("{:#}", err)
+                    .starts_with("Invalid config file: Invalid command in cycle: 0:3:x"),
+                "{:#}",
+                err
+            );
+        });
+    }
+
+    #[test]
+    fn windows_without_number_are_identified_by_their_frame() {
+        let backend = FakeBackend::new(desktop());
+        let terminal = WindowRef {
+            pid: 10,
+            number: None,
+        };
+        let identify = |x| {
+            let frame = Rect {
+                x,
+                y: 100,
+                width: 800,
+                height: 600,
+            };
+            identify_window(&backend, &terminal, &frame)
+                .unwrap()
+                .0
+                .number
+        };
+        assert_eq!(identify(100), Some(1));
+        assert_eq!(identify(101), Some(1));
+        assert_eq!(identify(120), None);
+    }
+
+    fn maximize_args() -> MaximizeArgs {
+        MaximizeArgs {
+            move_command: "s".to_string(),
+        }
+    }
+
+    #[test]
+    fn maximize_toggles_back_to_the_previous_frame() {
+        with_data_dir(|| {
+            let mut backend = fake_backend();
+            let before = frame_of(&mut backend, 10, 1);
+            maximize(&mut backend, maximize_args()).unwrap();
+            let screen = desktop().screens[0].visible_frame.clone();
+            assert_eq!(frame_of(&mut backend, 10, 1), screen);
+            maximize(&mut backend, maximize_args()).unwrap();
+            assert_eq!(frame_of(&mut backend, 10, 1), before);
+            assert!(State::load().unwrap().maximized.is_empty());
+        });
+    }
+
+    #[test]
+    fn maximize_forgets_only_closed_windows() {
+        with_data_dir(|| {
+            let saved = Rect {
+                x: 10,
+                y: 10,
+                width: 10,
+                height: 10,
+            };
+            let mut state = State::default();
+            // The browser, known by its process only, and a window that was closed since.
+            for key in ["20", "30:7"] {
+                state.maximized.insert(key.to_string(), saved.clone());
+            }
+            state.save().unwrap();
+            maximize(&mut fake_backend(), maximize_args()).unwrap();
+            let mut keys = State::load()
+                .unwrap()
+                .maximized
+                .into_keys()
+                .collect::<Vec<_>>();
+            keys.sort();
+            assert_eq!(keys, ["10:1", "20"]);
+        });
+    }
+
+    #[test]
+    fn windows_lists_the_fake_windows() {
+        let backend = FakeBackend::new(desktop());
+        windows(&backend, WindowsArgs {}).unwrap();
+        assert_eq!(backend.window_list(true).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn space_names_are_set_and_reset() {
+        with_data_dir(|| {
+            let backend = FakeBackend::new(desktop());
+            assert_eq!(space_name(&backend, None).unwrap(), "Unnamed");
+            let set = |id, name: Option<&str>| {
+                let args = SetSpaceNameArgs {
+                    id,
+                    name: name.map(str::to_string),
+                };
+                set_space_name(&backend, args).unwrap();
+            };
+            set(None, Some("mail"));
+            set(Some(4), Some("chat"));
+            assert_eq!(space_name(&backend, None).unwrap(), "mail");
+            assert_eq!(space_name(&backend, Some(3)).unwrap(), "mail");
+            assert_eq!(space_name(&backend, Some(4)).unwrap(), "chat");
+            set(None, None);
+            assert_eq!(space_name(&backend, None).unwrap(), "Unnamed");
+        });
+    }
+
+    /// The desktop with the browser focused instead of the terminal.
+    fn browser_focused() -> FakeBackend {
+        let mut desktop = desktop();
+        desktop.focused = Some(2);
+        FakeBackend::new(desktop)
+    }
+
+    #[test]
+    fn undo_skips_moves_of_closed_windows() {
+        with_data_dir(|| {
+            let mut backend = fake_backend();
+            move_window(&mut backend, move_args("021", None)).unwrap();
+            let mut browser = LazyBackend {
+                spec: None,
+                backend: Some(Box::new(browser_focused())),
+            };
+            move_window(&mut browser, move_args("020", None)).unwrap();
+
+            // The browser was closed since.
+            let mut desktop = desktop();
+            desktop.windows.retain(|w| w.number != 2);
+            desktop.windows[0].bounds = RIGHT_HALF;
+            let mut backend = FakeBackend::new(desktop);
+
+            undo(&mut backend, UndoArgs {}).unwrap();
+            let terminal = WindowRef {
+                pid: 10,
+                number: Some(1),
+            };
+            assert_eq!(backend.get_frame(&terminal).unwrap().x, 100);
+            let history = History::load().unwrap();
+            assert!(history.undo.is_empty());
+            assert_eq!(history.redo.len(), 1);
+            assert!(undo(&mut backend, UndoArgs {}).is_err());
+
+            redo(&mut backend, RedoArgs {}).unwrap();
+            assert_eq!(backend.get_frame(&terminal).unwrap(), RIGHT_HALF);
+        });
+    }
+
+    #[test]
+    fn redo_drops_entries_of_closed_windows() {
+        with_data_dir(|| {
+            let mut backend = LazyBackend {
+                spec: None,
+                backend: Some(Box::new(browser_focused())),
+            };
+            move_window(&mut backend, move_args("021", None)).unwrap();
+            undo(backend.get().unwrap(), UndoArgs {}).unwrap();
+
+            let mut desktop = desktop();
+            desktop.windows.retain(|w| w.number != 2);
+            let mut backend = FakeBackend::new(desktop);
+            assert!(redo(&mut backend, RedoArgs {}).is_err());
+            assert!(History::load().unwrap().redo.is_empty());
+        });
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn files_go_to_the_xdg_directories() {
+        with_data_dir(|| {
+            let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+            assert_eq!(config_dir().unwrap(), home.join(".config/move_window"));
+            assert_eq!(state_dir().unwrap(), home.join(".local/state/move_window"));
+
+            let xdg = tempfile::tempdir().unwrap();
+            // SAFETY: we hold the lock of `with_data_dir`.
+            unsafe {
+                std::env::set_var("XDG_CONFIG_HOME", xdg.path().join("config"));
+                std::env::set_var("XDG_STATE_HOME", "relative/state");
+            }
+            assert_eq!(config_dir().unwrap(), xdg.path().join("config/move_window"));
+            assert_eq!(state_dir().unwrap(), home.join(".local/state/move_window"));
+        });
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn backends_are_detected_from_the_environment() {
+        let detect = |vars: &[(&str, &str)]| {
+            detect_backend(|name| {
+                vars.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, value)| OsString::from(value))
+            })
+        };
+        let wayland = [("WAYLAND_DISPLAY", "wayland-1"), ("DISPLAY", ":0")];
+        assert_eq!(
+            detect(&[
+                ("HYPRLAND_INSTANCE_SIGNATURE", "abc_1_2"),
+                wayland[0],
+                wayland[1]
+            ])
+            .unwrap(),
+            "hyprland"
+        );
+        assert_eq!(
+            detect(&[
+                ("SWAYSOCK", "/run/user/1000/sway-ipc.sock"),
+                wayland[0],
+                wayland[1]
+            ])
+            .unwrap(),
+            "sway"
+        );
+        assert_eq!(
+            detect(&[("I3SOCK", "/tmp/i3-ipc.sock"), ("DISPLAY", ":0")]).unwrap(),
+            "sway"
+        );
+        assert_eq!(detect(&[("DISPLAY", ":0")]).unwrap(), "x11");
+        assert!(
+            detect(&[
+                ("HYPRLAND_INSTANCE_SIGNATURE", ""),
+                ("WAYLAND_DISPLAY", "wayland-1")
+            ])
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::backend::Window;
+use regex_lite::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// Picks a window out of the window list, for commands that should not act on the focused window.
+#[derive(Debug, Clone)]
+pub enum WindowSelector {
+    /// The window number as printed by `windows`.
+    Number(u32),
+    /// A window of the process with this pid.
+    Pid(i32),
+    /// A window of the application with this name, ignoring case.
+    App(String),
+    /// A window whose title matches this regex.
+    Title(Regex),
+}
+
+impl WindowSelector {
+    /// Returns the window in `windows` that this selector picks. A window number picks exactly
+    /// that window. Otherwise, we only consider normal windows, i.e. no menu bar items or
+    /// overlays, and take the frontmost match, preferring windows that are on screen.
+    pub fn select<'a>(&self, windows: &'a [Window]) -> Option<&'a Window> {
+        if let WindowSelector::Number(number) = *self {
+            return windows.iter().find(|w| w.number == number);
+        }
+        let mut candidates = windows
+            .iter()
+            .filter(|w| w.layer == 0 && self.matches(w))
+            .collect::<Vec<_>>();
+        // The window list is ordered front to back and sorting is stable.
+        candidates.sort_by_key(|w| !w.on_screen);
+        candidates.first().copied()
+    }
+
+    fn matches(&self, window: &Window) -> bool {
+        match self {
+            WindowSelector::Number(number) => window.number == *number,
+            WindowSelector::Pid(pid) => window.owner_pid == *pid,
+            WindowSelector::App(name) => window.owner_name.eq_ignore_ascii_case(name),
+            WindowSelector::Title(regex) => window
+                .name
+                .as_deref()
+                .is_some_and(|name| regex.is_match(name)),
+        }
+    }
+}
+
+impl FromStr for WindowSelector {
+    type Err = String;
+
+    /// Parses `123` or `number:123`, `pid:456`, `app:Safari` and `title:<regex>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s.split_once(':').unwrap_or(("number", s));
+        match kind {
+            "number" => value
+                .parse()
+                .map(WindowSelector::Number)
+                .map_err(|_| format!("invalid window number {:?}", value)),
+            "pid" => value
+                .parse()
+                .map(WindowSelector::Pid)
+                .map_err(|_| format!("invalid pid {:?}", value)),
+            "app" if value.is_empty() => Err("the app name is empty".to_string()),
+            "app" => Ok(WindowSelector::App(value.to_string())),
+            "title" => Regex::new(value)
+                .map(WindowSelector::Title)
+                .map_err(|e| format!("invalid title regex {:?}: {}", value, e)),
+            _ => Err(format!(
+                "unknown window selector {:?}, use a window number, pid:<pid>, app:<name> or \
+                 title:<regex>",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for WindowSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowSelector::Number(number) => write!(f, "{}", number),
+            WindowSelector::Pid(pid) => write!(f, "pid:{}", pid),
+            WindowSelector::App(name) => write!(f, "app:{}", name),
+            WindowSelector::Title(regex) => write!(f, "title:{}", regex.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rect;
+
+    fn window(
+        number: u32,
+        pid: i32,
+        app: &str,
+        title: &str,
+        layer: i32,
+        on_screen: bool,
+    ) -> Window {
+        Window {
+            name: Some(title.to_string()),
+            owner_pid: pid,
+            layer,
+            number,
+            owner_name: app.to_string(),
+            bounds: Rect {
+                x: 0,
+                y: 0,
+                width: 800,
+                height: 600,
+            },
+            alpha: 1.0,
+            on_screen,
+        }
+    }
+
+    fn select(selector: &str, windows: &[Window]) -> Option<u32> {
+        selector
+            .parse::<WindowSelector>()
+            .unwrap()
+            .select(windows)
+            .map(|w| w.number)
+    }
+
+    #[test]
+    fn parses_selectors() {
+        let parse = |s: &str| s.parse::<WindowSelector>().unwrap();
+        assert!(matches!(parse("123"), WindowSelector::Number(123)));
+        assert!(matches!(parse("number:123"), WindowSelector::Number(123)));
+        assert!(matches!(parse("pid:456"), WindowSelector::Pid(456)));
+        assert!(matches!(parse("app:Safari"), WindowSelector::App(name) if name == "Safari"));
+        assert!(
+            matches!(parse("title:^In:bo"), WindowSelector::Title(r) if r.as_str() == "^In:bo")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_selectors() {
+        for s in ["abc", "-1", "pid:x", "app:", "title:(", "foo:bar", ""] {
+            assert!(s.parse::<WindowSelector>().is_err(), "{:?}", s);
+        }
+        let err = "title:(".parse::<WindowSelector>().unwrap_err();
+        assert!(err.starts_with("invalid title regex \"(\""), "{}", err);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in ["123", "pid:456", "app:Safari", "title:^In"] {
+            let selector: WindowSelector = s.parse().unwrap();
+            assert_eq!(selector.to_string(), s);
+        }
+        let selector: WindowSelector = "number:7".parse().unwrap();
+        assert_eq!(selector.to_string(), "7");
+    }
+
+    #[test]
+    fn numbers_and_pids_are_different_selectors() {
+        let windows = [
+            window(10, 20, "Terminal", "zsh", 0, true),
+            window(20, 10, "Safari", "Inbox", 0, true),
+        ];
+        assert_eq!(select("10", &windows), Some(10));
+        assert_eq!(select("pid:10", &windows), Some(20));
+        assert_eq!(select("30", &windows), None);
+        assert_eq!(select("pid:30", &windows), None);
+    }
+
+    #[test]
+    fn only_a_number_selects_windows_outside_the_normal_layer() {
+        let windows = [
+            window(1, 10, "Dock", "Dock", 20, true),
+            window(2, 10, "Dock", "Dock", 0, false),
+        ];
+        assert_eq!(select("1", &windows), Some(1));
+        assert_eq!(select("pid:10", &windows), Some(2));
+        assert_eq!(select("app:Dock", &windows), Some(2));
+        assert_eq!(select("title:Dock", &windows), Some(2));
+        assert_eq!(select("pid:10", &windows[..1]), None);
+    }
+
+    #[test]
+    fn prefers_frontmost_windows_on_screen() {
+        let windows = [
+            window(1, 10, "Safari", "Inbox", 0, false),
+            window(2, 10, "Safari", "News", 0, true),
+            window(3, 10, "Safari", "Inbox (2)", 0, true),
+        ];
+        assert_eq!(select("pid:10", &windows), Some(2));
+        assert_eq!(select("app:safari", &windows), Some(2));
+        assert_eq!(select("title:^Inbox", &windows), Some(3));
+        assert_eq!(select("title:^Inbox$", &windows), Some(1));
+        assert_eq!(select("app:Safar", &windows), None);
+    }
+}
@@ -0,0 +1,542 @@
+use crate::Rect;
+use crate::backend::{Screen, Window, WindowBackend, WindowRef};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+/// Every message of the IPC protocol starts with this.
+const MAGIC: &[u8] = b"i3-ipc";
+
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+
+#[derive(Deserialize)]
+struct Output {
+    name: String,
+    #[serde(default)]
+    active: bool,
+    #[serde(default)]
+    primary: bool,
+    rect: Rect,
+    /// only reported by sway
+    scale: Option<f64>,
+    make: Option<String>,
+    model: Option<String>,
+    serial: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    id: u64,
+    num: i64,
+    name: String,
+    visible: bool,
+    focused: bool,
+    output: String,
+    rect: Rect,
+}
+
+#[derive(Deserialize)]
+struct WindowProperties {
+    class: Option<String>,
+}
+
+/// A container of the layout tree. Leaves with a `pid` (sway) or an X11 `window` (i3) are
+/// windows.
+#[derive(Deserialize)]
+struct Node {
+    id: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    rect: Rect,
+    #[serde(default)]
+    focused: bool,
+    /// only reported by sway
+    visible: Option<bool>,
+    pid: Option<i32>,
+    window: Option<u32>,
+    app_id: Option<String>,
+    window_properties: Option<WindowProperties>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    floating_nodes: Vec<Node>,
+}
+
+#[derive(Deserialize)]
+struct CommandResult {
+    success: bool,
+    error: Option<String>,
+}
+
+impl Node {
+    fn is_window(&self) -> bool {
+        matches!(self.kind.as_str(), "con" | "floating_con")
+            && (self.pid.is_some() || self.window.is_some())
+    }
+
+    /// The number we hand out for this window: the container id if it fits, which it does in sway,
+    /// otherwise the X11 window id, since i3 uses pointers as container ids.
+    fn number(&self) -> Option<u32> {
+        u32::try_from(self.id).ok().or(self.window)
+    }
+
+    /// The criteria that select this window in a command.
+    fn criteria(&self) -> String {
+        match u32::try_from(self.id) {
+            Ok(id) => format!("[con_id={}]", id),
+            Err(_) => format!("[id={}]", self.window.unwrap_or_default()),
+        }
+    }
+
+    /// Calls `f` with every window below this node and the name of the workspace it is on.
+    fn windows<'a>(
+        &'a self,
+        workspace: Option<&'a str>,
+        f: &mut impl FnMut(&'a Node, Option<&'a str>),
+    ) {
+        let workspace = match self.kind.as_str() {
+            "workspace" => self.name.as_deref(),
+            _ => workspace,
+        };
+        if self.is_window() {
+            f(self, workspace);
+        }
+        for node in self.nodes.iter().chain(&self.floating_nodes) {
+            node.windows(workspace, f);
+        }
+    }
+}
+
+/// The backend for sway and i3, which are both controlled through the i3 IPC protocol. Moving a
+/// window makes it floating, since tiled windows cannot be placed freely.
+pub struct SwayBackend {
+    stream: UnixStream,
+}
+
+impl SwayBackend {
+    /// Connects to the IPC socket named by `$SWAYSOCK`, or `$I3SOCK` for i3.
+    pub fn connect() -> Result<Self> {
+        let path = std::env::var_os("SWAYSOCK")
+            .or_else(|| std::env::var_os("I3SOCK"))
+            .context("Neither $SWAYSOCK nor $I3SOCK is set")?;
+        Self::connect_to(Path::new(&path))
+    }
+
+    pub fn connect_to(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path)
+            .with_context(|| format!("Failed to connect to IPC socket {}", path.display()))?;
+        Ok(SwayBackend { stream })
+    }
+
+    /// Sends a message of type `kind` and returns the payload of the reply.
+    fn request(&self, kind: u32, payload: &str) -> Result<Vec<u8>> {
+        let mut stream = &self.stream;
+        let mut message = MAGIC.to_vec();
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        stream
+            .write_all(&message)
+            .context("Failed to send IPC message")?;
+
+        let mut header = [0; 14];
+        stream
+            .read_exact(&mut header)
+            .context("Failed to read IPC reply")?;
+        if &header[..6] != MAGIC {
+            bail!("Invalid IPC reply.");
+        }
+        let length = u32::from_ne_bytes(header[6..10].try_into().expect("4 bytes."));
+        let reply_kind = u32::from_ne_bytes(header[10..14].try_into().expect("4 bytes."));
+        if reply_kind != kind {
+            bail!(
+                "Expected an IPC reply of type {}, got {}.",
+                kind,
+                reply_kind
+            );
+        }
+        let mut reply = vec![0; length as usize];
+        stream
+            .read_exact(&mut reply)
+            .context("Failed to read IPC reply")?;
+        Ok(reply)
+    }
+
+    fn get<T: DeserializeOwned>(&self, kind: u32) -> Result<T> {
+        let reply = self.request(kind, "")?;
+        serde_json::from_slice(&reply).context("Failed to parse IPC reply")
+    }
+
+    fn run_command(&self, command: &str) -> Result<()> {
+        let reply = self.request(RUN_COMMAND, command)?;
+        let results: Vec<CommandResult> =
+            serde_json::from_slice(&reply).context("Failed to parse IPC reply")?;
+        for result in results {
+            if !result.success {
+                bail!(
+                    "Command {:?} failed: {}",
+                    command,
+                    result.error.unwrap_or_default()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `f` with the tree node of `window`.
+    fn with_node<T>(&self, window: &WindowRef, f: impl FnOnce(&Node) -> T) -> Result<T> {
+        let Some(number) = window.number else {
+            bail!("The sway backend needs a window id.");
+        };
+        let tree: Node = self.get(GET_TREE)?;
+        let mut found = None;
+        tree.windows(None, &mut |node, _| {
+            if found.is_none() && node.number() == Some(number) {
+                found = Some(node);
+            }
+        });
+        match found {
+            Some(node) => Ok(f(node)),
+            None => bail!("Unknown window {}.", number),
+        }
+    }
+}
+
+impl WindowBackend for SwayBackend {
+    fn screens(&self) -> Result<Vec<Screen>> {
+        let mut outputs: Vec<Output> = self.get(GET_OUTPUTS)?;
+        outputs.retain(|o| o.active);
+        // The primary output is the main screen. Only i3 has one.
+        outputs.sort_by_key(|o| !o.primary);
+        let workspaces: Vec<Workspace> = self.get(GET_WORKSPACES)?;
+        Ok(outputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, output)| {
+                // The visible workspace of an output leaves out the bars.
+                let visible_frame = workspaces
+                    .iter()
+                    .find(|w| w.visible && w.output == output.name)
+                    .map(|w| w.rect.clone())
+                    .unwrap_or_else(|| output.rect.clone());
+                let hardware = [&output.make, &output.model, &output.serial]
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                Screen {
+                    index: index as u64,
+                    id: if hardware.is_empty() {
+                        output.name.clone()
+                    } else {
+                        hardware.join(" ")
+                    },
+                    name: output.name,
+                    visible_frame,
+                    frame: output.rect,
+                    scale_factor: output.scale.unwrap_or(1.),
+                }
+            })
+            .collect())
+    }
+
+    fn focused_window(&self) -> Result<WindowRef> {
+        let tree: Node = self.get(GET_TREE)?;
+        let mut found = None;
+        tree.windows(None, &mut |node, _| {
+            if node.focused {
+                found = Some(WindowRef {
+                    pid: node.pid.unwrap_or(0),
+                    number: node.number(),
+                });
+            }
+        });
+        match found {
+            Some(window) => Ok(window),
+            None => bail!("No window has the focus."),
+        }
+    }
+
+    fn window_list(&self, all_windows: bool) -> Result<Vec<Window>> {
+        let tree: Node = self.get(GET_TREE)?;
+        let workspaces: Vec<Workspace> = self.get(GET_WORKSPACES)?;
+        let mut rv = Vec::new();
+        tree.windows(None, &mut |node, workspace| {
+            let on_screen = node.visible.unwrap_or_else(|| {
+                workspaces
+                    .iter()
+                    .any(|w| w.visible && Some(w.name.as_str()) == workspace)
+            });
+            let Some(number) = node.number() else {
+                return;
+            };
+            if !all_windows && !on_screen {
+                return;
+            }
+            let owner_name = node
+                .app_id
+                .clone()
+                .or_else(|| node.window_properties.as_ref()?.class.clone())
+                .unwrap_or_default();
+            rv.push(Window {
+                name: node.name.clone(),
+                owner_pid: node.pid.unwrap_or(0),
+                layer: 0,
+                number,
+                owner_name,
+                bounds: node.rect.clone(),
+                alpha: 1.,
+                on_screen,
+            });
+        });
+        Ok(rv)
+    }
+
+    fn get_frame(&self, window: &WindowRef) -> Result<Rect> {
+        self.with_node(window, |node| node.rect.clone())
+    }
+
+    fn set_frame(&mut self, window: &WindowRef, frame: &Rect) -> Result<()> {
+        let criteria = self.with_node(window, Node::criteria)?;
+        self.run_command(&format!(
+            "{} floating enable, resize set width {} px height {} px, move absolute position {} px {} px",
+            criteria, frame.width, frame.height, frame.x, frame.y
+        ))
+    }
+
+    /// The number of the focused workspace, or its container id if it has no number.
+    fn current_space_id(&self) -> Result<u64> {
+        let workspaces: Vec<Workspace> = self.get(GET_WORKSPACES)?;
+        match workspaces.into_iter().find(|w| w.focused) {
+            Some(w) => Ok(u64::try_from(w.num).unwrap_or(w.id)),
+            None => bail!("No workspace has the focus."),
+        }
+    }
+
+    fn space_name(&self, id: u64) -> Result<Option<String>> {
+        let workspaces: Vec<Workspace> = self.get(GET_WORKSPACES)?;
+        Ok(workspaces
+            .into_iter()
+            .find(|w| u64::try_from(w.num).unwrap_or(w.id) == id)
+            .map(|w| w.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::thread::JoinHandle;
+
+    const OUTPUTS: &str = r#"[
+        {"name": "eDP-1", "active": true, "primary": false, "scale": 2.0,
+         "rect": {"x": 0, "y": 0, "width": 1440, "height": 900},
+         "make": "Sharp Corporation", "model": "0x1449", "serial": "0x00000000"},
+        {"name": "HDMI-A-1", "active": false, "primary": false,
+         "rect": {"x": 0, "y": 0, "width": 0, "height": 0}},
+        {"name": "DP-2", "active": true, "primary": false, "scale": 1.0,
+         "rect": {"x": 1440, "y": 0, "width": 1920, "height": 1080}}
+    ]"#;
+
+    const WORKSPACES: &str = r#"[
+        {"id": 4, "num": 1, "name": "1: web", "visible": true, "focused": false, "output": "eDP-1",
+         "rect": {"x": 0, "y": 30, "width": 1440, "height": 870}},
+        {"id": 7, "num": -1, "name": "mail", "visible": true, "focused": true, "output": "DP-2",
+         "rect": {"x": 1440, "y": 30, "width": 1920, "height": 1050}},
+        {"id": 9, "num": 3, "name": "3", "visible": false, "focused": false, "output": "DP-2",
+         "rect": {"x": 1440, "y": 30, "width": 1920, "height": 1050}}
+    ]"#;
+
+    const TREE: &str = r#"{"id": 1, "type": "root", "rect": {"x": 0, "y": 0, "width": 3360, "height": 1080},
+        "nodes": [{"id": 2, "type": "output", "name": "eDP-1", "rect": {"x": 0, "y": 0, "width": 1440, "height": 900},
+            "nodes": [
+                {"id": 4, "type": "workspace", "name": "1: web", "rect": {"x": 0, "y": 30, "width": 1440, "height": 870},
+                 "nodes": [{"id": 12, "type": "con", "name": "Inbox", "pid": 20, "app_id": "firefox", "visible": true,
+                     "focused": true, "rect": {"x": 0, "y": 30, "width": 1440, "height": 870}}]},
+                {"id": 9, "type": "workspace", "name": "3", "rect": {"x": 1440, "y": 30, "width": 1920, "height": 1050},
+                 "floating_nodes": [{"id": 13, "type": "floating_con", "name": "~", "pid": 10, "window": 4194307,
+                     "window_properties": {"class": "XTerm"}, "visible": false,
+                     "rect": {"x": 1500, "y": 100, "width": 800, "height": 600}}]}
+            ]}]}"#;
+
+    /// Serves `replies` in order to the first client and returns the type and payload of every
+    /// message it received, checking the framing on the way.
+    fn serve(replies: Vec<(u32, &'static str)>) -> (SwayBackend, JoinHandle<Vec<(u32, String)>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ipc.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            let _dir = dir;
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            for (kind, reply) in replies {
+                let mut header = [0; 14];
+                stream.read_exact(&mut header).unwrap();
+                assert_eq!(&header[..6], MAGIC);
+                let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+                let mut payload = vec![0; length as usize];
+                stream.read_exact(&mut payload).unwrap();
+                received.push((
+                    u32::from_ne_bytes(header[10..14].try_into().unwrap()),
+                    String::from_utf8(payload).unwrap(),
+                ));
+
+                let mut message = MAGIC.to_vec();
+                message.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+                message.extend_from_slice(&kind.to_ne_bytes());
+                message.extend_from_slice(reply.as_bytes());
+                stream.write_all(&message).unwrap();
+            }
+            received
+        });
+        (SwayBackend::connect_to(&path).unwrap(), server)
+    }
+
+    #[test]
+    fn screens_are_active_outputs_without_bars() {
+        let (backend, server) = serve(vec![(GET_OUTPUTS, OUTPUTS), (GET_WORKSPACES, WORKSPACES)]);
+        let screens = backend.screens().unwrap();
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                (GET_OUTPUTS, String::new()),
+                (GET_WORKSPACES, String::new())
+            ]
+        );
+
+        assert_eq!(screens.len(), 2);
+        assert_eq!(screens[0].id, "Sharp Corporation 0x1449 0x00000000");
+        assert_eq!(screens[0].name, "eDP-1");
+        assert_eq!(screens[0].scale_factor, 2.);
+        assert_eq!(
+            screens[0].visible_frame,
+            Rect {
+                x: 0,
+                y: 30,
+                width: 1440,
+                height: 870
+            }
+        );
+        assert_eq!((screens[1].index, screens[1].id.as_str()), (1, "DP-2"));
+        assert_eq!(screens[1].frame.x, 1440);
+    }
+
+    #[test]
+    fn windows_are_the_leaves_of_the_tree() {
+        let (backend, server) = serve(vec![
+            (GET_TREE, TREE),
+            (GET_WORKSPACES, WORKSPACES),
+            (GET_TREE, TREE),
+            (GET_WORKSPACES, WORKSPACES),
+            (GET_TREE, TREE),
+        ]);
+        let visible = backend.window_list(false).unwrap();
+        let all = backend.window_list(true).unwrap();
+        let focused = backend.focused_window().unwrap();
+        server.join().unwrap();
+
+        assert_eq!(visible.len(), 1);
+        assert_eq!(
+            (visible[0].number, visible[0].owner_name.as_str()),
+            (12, "firefox")
+        );
+        assert_eq!(all.len(), 2);
+        assert_eq!(
+            (all[1].owner_name.as_str(), all[1].on_screen),
+            ("XTerm", false)
+        );
+        assert_eq!(
+            focused,
+            WindowRef {
+                pid: 20,
+                number: Some(12)
+            }
+        );
+    }
+
+    #[test]
+    fn set_frame_floats_and_places_the_window() {
+        let (mut backend, server) = serve(vec![
+            (GET_TREE, TREE),
+            (RUN_COMMAND, r#"[{"success": true}]"#),
+        ]);
+        let window = WindowRef {
+            pid: 10,
+            number: Some(13),
+        };
+        let frame = Rect {
+            x: 2400,
+            y: 30,
+            width: 960,
+            height: 1050,
+        };
+        backend.set_frame(&window, &frame).unwrap();
+        assert_eq!(
+            server.join().unwrap()[1],
+            (
+                RUN_COMMAND,
+                "[con_id=13] floating enable, resize set width 960 px height 1050 px, move \
+                 absolute position 2400 px 30 px"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn failed_commands_are_errors() {
+        let (mut backend, server) = serve(vec![
+            (GET_TREE, TREE),
+            (
+                RUN_COMMAND,
+                r#"[{"success": false, "error": "No matching node"}]"#,
+            ),
+        ]);
+        let window = WindowRef {
+            pid: 20,
+            number: Some(12),
+        };
+        let frame = Rect {
+            x: 0,
+            y: 30,
+            width: 720,
+            height: 870,
+        };
+        let err = backend.set_frame(&window, &frame).unwrap_err();
+        server.join().unwrap();
+        assert!(
+            err.to_string().ends_with("failed: No matching node"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn replies_of_the_wrong_type_are_errors() {
+        let (backend, server) = serve(vec![(GET_TREE, WORKSPACES)]);
+        let err = backend.current_space_id().unwrap_err();
+        server.join().unwrap();
+        assert_eq!(err.to_string(), "Expected an IPC reply of type 1, got 4.");
+    }
+
+    #[test]
+    fn spaces_are_workspaces_by_number_or_id() {
+        let (backend, server) = serve(vec![
+            (GET_WORKSPACES, WORKSPACES),
+            (GET_WORKSPACES, WORKSPACES),
+            (GET_WORKSPACES, WORKSPACES),
+            (GET_WORKSPACES, WORKSPACES),
+        ]);
+        assert_eq!(backend.current_space_id().unwrap(), 7);
+        assert_eq!(backend.space_name(7).unwrap().as_deref(), Some("mail"));
+        assert_eq!(backend.space_name(1).unwrap().as_deref(), Some("1: web"));
+        assert_eq!(backend.space_name(4).unwrap(), None);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use crate::command::{MoveParameters, ScreenSelector, is_area_char};
+use anyhow::{Result, bail};
+
+/// A grid whose cells carry names, like `grid-template-areas` in CSS. Each row is a string of cell
+/// names separated by whitespace, e.g. `["editor editor term", "editor editor logs"]`. Cells with
+/// the same name form an area, which must be a rectangle. Cells named `.` belong to no area.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    cells: Vec<Vec<String>>,
+}
+
+impl Template {
+    /// Parses and validates the rows of a template.
+    pub fn parse(rows: &[String]) -> Result<Self> {
+        let cells = rows
+            .iter()
+            .map(|row| row.split_whitespace().map(str::to_string).collect())
+            .collect::<Vec<Vec<String>>>();
+        let Some(columns) = cells.first().map(Vec::len) else {
+            bail!("The template has no rows.");
+        };
+        if columns == 0 {
+            bail!("The first row of the template has no cells.");
+        }
+        for (y, row) in cells.iter().enumerate() {
+            if row.len() != columns {
+                bail!(
+                    "Row {} of the template has {} cells, but the first row has {}.",
+                    y,
+                    row.len(),
+                    columns
+                );
+            }
+            if let Some(name) = row.iter().find(|name| *name != "." && !is_area_name(name)) {
+                bail!(
+                    "Invalid area name {:?} in the template, names start with a letter and consist of letters, digits, '-' and '_'.",
+                    name
+                );
+            }
+        }
+        let template = Template { cells };
+        for name in template.names() {
+            let (x_start, x_end, y_start, y_end) = template.bounds(name);
+            let rectangular =
+                (y_start..=y_end).all(|y| (x_start..=x_end).all(|x| template.cells[y][x] == name));
+            if !rectangular {
+                bail!("Area {:?} of the template is not a rectangle.", name);
+            }
+        }
+        Ok(template)
+    }
+
+    /// The names of all areas, in the order they first appear.
+    pub fn names(&self) -> Vec<&str> {
+        let mut rv = Vec::new();
+        for name in self.cells.iter().flatten() {
+            if name != "." && !rv.contains(&name.as_str()) {
+                rv.push(name.as_str());
+            }
+        }
+        rv
+    }
+
+    /// The grid cells that make up the area `name` on the screen selected by `screen`, or `None` if
+    /// there is no such area.
+    pub fn area(&self, screen: ScreenSelector, name: &str) -> Option<MoveParameters> {
+        if name == "." || !self.cells.iter().flatten().any(|n| n == name) {
+            return None;
+        }
+        let (x_start, x_end, y_start, y_end) = self.bounds(name);
+        Some(MoveParameters {
+            screen,
+            x_ratio: self.cells[0].len() as i32,
+            y_ratio: self.cells.len() as i32,
+            x_weights: Vec::new(),
+            y_weights: Vec::new(),
+            x_start: x_start as i32,
+            x_end: x_end as i32,
+            y_start: y_start as i32,
+            y_end: y_end as i32,
+        })
+    }
+
+    /// The first and last column and the first and last row containing a cell named `name`, which
+    /// must exist.
+    fn bounds(&self, name: &str) -> (usize, usize, usize, usize) {
+        let positions = self.cells.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(move |(_, n)| *n == name)
+                .map(move |(x, _)| (x, y))
+        });
+        let mut bounds = (usize::MAX, 0, usize::MAX, 0);
+        for (x, y) in positions {
+            bounds = (
+                bounds.0.min(x),
+                bounds.1.max(x),
+                bounds.2.min(y),
+                bounds.3.max(y),
+            );
+        }
+        bounds
+    }
+}
+
+/// Whether `name` can be selected as `screen:name`, which needs a letter first to tell it apart
+/// from a grid.
+fn is_area_name(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_alphabetic) && name.chars().all(is_area_char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(rows: &[&str]) -> Result<Template> {
+        Template::parse(&rows.iter().map(|r| r.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn areas_cover_their_cells() {
+        let template = parse(&["editor editor term", "editor editor logs", ". . logs"]).unwrap();
+        assert_eq!(template.names(), ["editor", "term", "logs"]);
+        let area = |name| {
+            template
+                .area(ScreenSelector::Index(1), name)
+                .map(|p| (p.x_ratio, p.x_start, p.x_end, p.y_ratio, p.y_start, p.y_end))
+        };
+        assert_eq!(area("editor"), Some((3, 0, 1, 3, 0, 1)));
+        assert_eq!(area("term"), Some((3, 2, 2, 3, 0, 0)));
+        assert_eq!(area("logs"), Some((3, 2, 2, 3, 1, 2)));
+        assert_eq!(area("."), None);
+        assert_eq!(area("mail"), None);
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&[""]).is_err());
+        assert!(parse(&["a b", "a"]).is_err());
+        assert!(parse(&["a b a"]).is_err());
+        assert!(parse(&["a b", "b b"]).is_err());
+        assert!(parse(&["a b!"]).is_err());
+    }
+
+    #[test]
+    fn area_names_start_with_a_letter() {
+        assert!(parse(&["term-1 logs_2"]).is_ok());
+        for name in ["1term", "-term", "_term"] {
+            assert!(parse(&[name]).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,502 @@
+use crate::Rect;
+use crate::backend::{Screen, Window, WindowBackend, WindowRef};
+use crate::geometry::Insets;
+use anyhow::{Context, Result, bail};
+use x11rb::connection::Connection;
+use x11rb::errors::ConnectionError;
+use x11rb::protocol::randr::{ConnectionExt as _, MonitorInfo};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, EventMask,
+};
+use x11rb::rust_connection::RustConnection;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST,
+        _NET_CURRENT_DESKTOP,
+        _NET_FRAME_EXTENTS,
+        _NET_MOVERESIZE_WINDOW,
+        _NET_SUPPORTED,
+        _NET_WM_DESKTOP,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_STATE,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WORKAREA,
+    }
+}
+
+/// `_NET_WM_STATE` action that removes a state.
+const NET_WM_STATE_REMOVE: u32 = 0;
+
+/// `_NET_MOVERESIZE_WINDOW` flags: north west gravity, so that x and y are the outer top left
+/// corner of the frame, all of x, y, width and height given, and sent by a pager.
+const MOVERESIZE_FLAGS: u32 = 1 | 0b1111 << 8 | 2 << 12;
+
+/// `_NET_WM_DESKTOP` of windows that are shown on all desktops.
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+/// The backend for X11 desktops, talking to an EWMH compliant window manager. Frames include the
+/// window decorations, like on macOS.
+pub struct X11Backend {
+    conn: RustConnection,
+    screen_num: usize,
+    root: u32,
+    atoms: Atoms,
+}
+
+impl X11Backend {
+    /// Connects to the X server named by `$DISPLAY`.
+    pub fn connect() -> Result<Self> {
+        let (conn, screen_num) = x11rb::connect(None).context("Failed to connect to X server")?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        Ok(X11Backend {
+            conn,
+            screen_num,
+            root,
+            atoms,
+        })
+    }
+
+    /// Returns the property `property` of `window` as a list of 32 bit values, or an empty list
+    /// if it is not set.
+    fn cardinals(&self, window: u32, property: Atom) -> Result<Vec<u32>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()?;
+        Ok(reply.value32().map(|v| v.collect()).unwrap_or_default())
+    }
+
+    /// Returns the property `property` of `window` as a string, or `None` if it is not set.
+    fn text(&self, window: u32, property: Atom) -> Result<Option<String>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()?;
+        if reply.value.is_empty() {
+            return Ok(None);
+        }
+        let value = String::from_utf8_lossy(&reply.value);
+        Ok(Some(value.trim_end_matches('\0').to_string()))
+    }
+
+    /// The window's decorations.
+    fn frame_extents(&self, window: u32) -> Result<Insets> {
+        Ok(parse_frame_extents(
+            &self.cardinals(window, self.atoms._NET_FRAME_EXTENTS)?,
+        ))
+    }
+
+    /// The frame of `window` including its decorations, in root window coordinates.
+    fn outer_frame(&self, window: u32) -> Result<Rect> {
+        let geometry = self.conn.get_geometry(window)?.reply()?;
+        let position = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)?
+            .reply()?;
+        let client = Rect {
+            x: i32::from(position.dst_x),
+            y: i32::from(position.dst_y),
+            width: i32::from(geometry.width),
+            height: i32::from(geometry.height),
+        };
+        Ok(decorated(&client, &self.frame_extents(window)?))
+    }
+
+    fn current_desktop(&self) -> Result<u32> {
+        Ok(self
+            .cardinals(self.root, self.atoms._NET_CURRENT_DESKTOP)?
+            .first()
+            .copied()
+            .unwrap_or(0))
+    }
+
+    /// The area of the current desktop not covered by panels, if the window manager tells.
+    fn work_area(&self) -> Result<Option<Rect>> {
+        let desktop = self.current_desktop()?;
+        let workarea = self.cardinals(self.root, self.atoms._NET_WORKAREA)?;
+        Ok(desktop_work_area(&workarea, desktop))
+    }
+
+    fn window_number(window: &WindowRef) -> Result<u32> {
+        match window.number {
+            Some(number) => Ok(number),
+            None => bail!("The X11 backend needs a window id."),
+        }
+    }
+
+    /// Sends a client message about `window` to the root window, which is how EWMH asks the window
+    /// manager to change things.
+    fn send_to_root(&self, window: u32, message_type: Atom, data: [u32; 5]) -> Result<()> {
+        let event = ClientMessageEvent::new(32, window, message_type, data);
+        self.conn.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?;
+        Ok(())
+    }
+}
+
+impl WindowBackend for X11Backend {
+    fn screens(&self) -> Result<Vec<Screen>> {
+        // Without RandR, e.g. on some VNC servers, the whole root window is one screen.
+        let reply = match self.conn.randr_get_monitors(self.root, true) {
+            Ok(cookie) => cookie.reply().ok(),
+            Err(ConnectionError::UnsupportedExtension) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let mut monitors = match reply {
+            Some(reply) if !reply.monitors.is_empty() => reply.monitors,
+            _ => {
+                let screen = &self.conn.setup().roots[self.screen_num];
+                vec![MonitorInfo {
+                    name: AtomEnum::NONE.into(),
+                    primary: true,
+                    width: screen.width_in_pixels,
+                    height: screen.height_in_pixels,
+                    ..MonitorInfo::default()
+                }]
+            }
+        };
+        // The primary monitor is the main screen.
+        monitors.sort_by_key(|m| !m.primary);
+        let work_area = self.work_area()?;
+        let mut rv = Vec::new();
+        for (index, monitor) in monitors.into_iter().enumerate() {
+            let name = match monitor.name {
+                0 => "default".to_string(),
+                atom => {
+                    let name = self.conn.get_atom_name(atom)?.reply()?.name;
+                    String::from_utf8_lossy(&name).to_string()
+                }
+            };
+            let frame = Rect {
+                x: i32::from(monitor.x),
+                y: i32::from(monitor.y),
+                width: i32::from(monitor.width),
+                height: i32::from(monitor.height),
+            };
+            let visible_frame = visible_frame(&frame, work_area.as_ref());
+            rv.push(Screen {
+                index: index as u64,
+                id: name.clone(),
+                name,
+                visible_frame,
+                frame,
+                scale_factor: 1.,
+            });
+        }
+        Ok(rv)
+    }
+
+    fn focused_window(&self) -> Result<WindowRef> {
+        let active = self.cardinals(self.root, self.atoms._NET_ACTIVE_WINDOW)?;
+        let Some(&number) = active.first().filter(|w| **w != 0) else {
+            bail!("There is no active window.");
+        };
+        let pid = self.cardinals(number, self.atoms._NET_WM_PID)?;
+        Ok(WindowRef {
+            pid: pid.first().copied().unwrap_or(0) as i32,
+            number: Some(number),
+        })
+    }
+
+    fn window_list(&self, all_windows: bool) -> Result<Vec<Window>> {
+        let desktop = self.current_desktop()?;
+        let mut rv = Vec::new();
+        for number in self.cardinals(self.root, self.atoms._NET_CLIENT_LIST)? {
+            let window_desktop = self.cardinals(number, self.atoms._NET_WM_DESKTOP)?;
+            let state = self.cardinals(number, self.atoms._NET_WM_STATE)?;
+            let on_screen = is_on_screen(
+                &window_desktop,
+                &state,
+                desktop,
+                self.atoms._NET_WM_STATE_HIDDEN,
+            );
+            if !all_windows && !on_screen {
+                continue;
+            }
+            let name = match self.text(number, self.atoms._NET_WM_NAME)? {
+                Some(name) => Some(name),
+                None => self.text(number, AtomEnum::WM_NAME.into())?,
+            };
+            // WM_CLASS holds the instance and the class name, separated by a NUL.
+            let owner_name = self
+                .text(number, AtomEnum::WM_CLASS.into())?
+                .and_then(|class| class.split('\0').nth(1).map(str::to_string))
+                .unwrap_or_default();
+            let pid = self.cardinals(number, self.atoms._NET_WM_PID)?;
+            rv.push(Window {
+                name,
+                owner_pid: pid.first().copied().unwrap_or(0) as i32,
+                layer: 0,
+                number,
+                owner_name,
+                bounds: self.outer_frame(number)?,
+                alpha: 1.,
+                on_screen,
+            });
+        }
+        Ok(rv)
+    }
+
+    fn get_frame(&self, window: &WindowRef) -> Result<Rect> {
+        self.outer_frame(Self::window_number(window)?)
+    }
+
+    fn set_frame(&mut self, window: &WindowRef, frame: &Rect) -> Result<()> {
+        let number = Self::window_number(window)?;
+        let client = frame.inset(&self.frame_extents(number)?);
+        let (width, height) = (client.width as u32, client.height as u32);
+
+        // Window managers do not move maximized windows.
+        self.send_to_root(
+            number,
+            self.atoms._NET_WM_STATE,
+            [
+                NET_WM_STATE_REMOVE,
+                self.atoms._NET_WM_STATE_MAXIMIZED_HORZ,
+                self.atoms._NET_WM_STATE_MAXIMIZED_VERT,
+                2,
+                0,
+            ],
+        )?;
+
+        let supported = self.cardinals(self.root, self.atoms._NET_SUPPORTED)?;
+        if supported.contains(&self.atoms._NET_MOVERESIZE_WINDOW) {
+            self.send_to_root(
+                number,
+                self.atoms._NET_MOVERESIZE_WINDOW,
+                [
+                    MOVERESIZE_FLAGS,
+                    frame.x as u32,
+                    frame.y as u32,
+                    width,
+                    height,
+                ],
+            )?;
+        } else {
+            let aux = ConfigureWindowAux::new()
+                .x(client.x)
+                .y(client.y)
+                .width(width)
+                .height(height);
+            self.conn.configure_window(number, &aux)?;
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn current_space_id(&self) -> Result<u64> {
+        Ok(u64::from(self.current_desktop()?))
+    }
+}
+
+/// Reads `_NET_FRAME_EXTENTS`, the left, right, top and bottom decorations. Windows without the
+/// property have none.
+fn parse_frame_extents(values: &[u32]) -> Insets {
+    match *values {
+        [left, right, top, bottom] => Insets {
+            top: top as i32,
+            right: right as i32,
+            bottom: bottom as i32,
+            left: left as i32,
+        },
+        _ => Insets::default(),
+    }
+}
+
+/// The frame of a `client` window together with its decorations. `Rect::inset` is the way back.
+fn decorated(client: &Rect, extents: &Insets) -> Rect {
+    Rect {
+        x: client.x - extents.left,
+        y: client.y - extents.top,
+        width: client.width + extents.left + extents.right,
+        height: client.height + extents.top + extents.bottom,
+    }
+}
+
+/// Picks the work area of `desktop` from `_NET_WORKAREA`, which lists x, y, width and height of
+/// each desktop's work area.
+fn desktop_work_area(workarea: &[u32], desktop: u32) -> Option<Rect> {
+    workarea
+        .chunks_exact(4)
+        .nth(desktop as usize)
+        .map(|area| Rect {
+            x: area[0] as i32,
+            y: area[1] as i32,
+            width: area[2] as i32,
+            height: area[3] as i32,
+        })
+}
+
+/// The part of a monitor's `frame` inside the `work_area`. The work area spans all monitors, so
+/// it only cuts off panels at the outer edges of the desktop. Monitors that do not overlap it keep
+/// their whole frame.
+fn visible_frame(frame: &Rect, work_area: Option<&Rect>) -> Rect {
+    work_area
+        .and_then(|area| frame.intersection(area))
+        .unwrap_or_else(|| frame.clone())
+}
+
+/// Whether a window of `_NET_CLIENT_LIST` with the `_NET_WM_DESKTOP` and `_NET_WM_STATE` values
+/// `window_desktop` and `state` is shown on `desktop`. Windows without a desktop are on all of
+/// them.
+fn is_on_screen(window_desktop: &[u32], state: &[u32], desktop: u32, hidden: Atom) -> bool {
+    window_desktop
+        .first()
+        .is_none_or(|d| *d == desktop || *d == ALL_DESKTOPS)
+        && !state.contains(&hidden)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::{CreateWindowAux, WindowClass};
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn frame_extents_are_added_and_taken_away() {
+        let extents = parse_frame_extents(&[2, 3, 20, 4]);
+        assert_eq!(
+            extents,
+            Insets {
+                top: 20,
+                right: 3,
+                bottom: 4,
+                left: 2,
+            }
+        );
+        let client = rect(102, 120, 800, 600);
+        let outer = decorated(&client, &extents);
+        assert_eq!(outer, rect(100, 100, 805, 624));
+        assert_eq!(outer.inset(&extents), client);
+        // Frames smaller than their decorations still leave a pixel of the window.
+        assert_eq!(rect(0, 0, 4, 10).inset(&extents).width, 1);
+        for values in [&[][..], &[1, 2, 3]] {
+            assert_eq!(parse_frame_extents(values), Insets::default());
+        }
+    }
+
+    #[test]
+    fn work_areas_cut_off_panels_at_the_edges_of_the_desktop() {
+        // Two desktops, the second without the panel at the top.
+        let workarea = [0, 30, 3840, 1050, 0, 0, 3840, 1080];
+        assert_eq!(
+            desktop_work_area(&workarea, 0),
+            Some(rect(0, 30, 3840, 1050))
+        );
+        assert_eq!(
+            desktop_work_area(&workarea, 1),
+            Some(rect(0, 0, 3840, 1080))
+        );
+        assert_eq!(desktop_work_area(&workarea, 2), None);
+        assert_eq!(desktop_work_area(&[0, 30, 1920], 0), None);
+
+        let work_area = rect(0, 30, 3840, 1050);
+        let left = rect(0, 0, 1920, 1080);
+        let right = rect(1920, 0, 1920, 1080);
+        let below = rect(0, 1080, 1920, 1080);
+        assert_eq!(
+            visible_frame(&left, Some(&work_area)),
+            rect(0, 30, 1920, 1050)
+        );
+        assert_eq!(
+            visible_frame(&right, Some(&work_area)),
+            rect(1920, 30, 1920, 1050)
+        );
+        assert_eq!(visible_frame(&below, Some(&work_area)), below);
+        assert_eq!(visible_frame(&left, None), left);
+    }
+
+    #[test]
+    fn windows_of_other_desktops_and_hidden_ones_are_off_screen() {
+        const HIDDEN: Atom = 300;
+        const MAXIMIZED: Atom = 301;
+        for (window_desktop, state, on_screen) in [
+            (&[1][..], &[][..], true),
+            (&[1], &[MAXIMIZED], true),
+            (&[ALL_DESKTOPS], &[], true),
+            (&[], &[], true),
+            (&[0], &[], false),
+            (&[1], &[MAXIMIZED, HIDDEN], false),
+            (&[ALL_DESKTOPS], &[HIDDEN], false),
+        ] {
+            assert_eq!(
+                is_on_screen(window_desktop, state, 1, HIDDEN),
+                on_screen,
+                "{:?} {:?}",
+                window_desktop,
+                state
+            );
+        }
+    }
+
+    /// Runs against the X server named by `$DISPLAY`, e.g. with
+    /// `xvfb-run cargo test -- --ignored x11`.
+    #[test]
+    #[ignore = "needs an X server"]
+    fn moves_a_window_on_a_real_server() {
+        let mut backend = X11Backend::connect().expect("needs an X server, see $DISPLAY");
+        let screens = backend.screens().unwrap();
+        assert_eq!(screens[0].index, 0);
+        assert!(screens[0].frame.width > 0 && screens[0].frame.height > 0);
+        backend.current_space_id().unwrap();
+
+        let number = backend.conn.generate_id().unwrap();
+        backend
+            .conn
+            .create_window(
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                number,
+                backend.root,
+                0,
+                0,
+                100,
+                100,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                x11rb::COPY_FROM_PARENT,
+                &CreateWindowAux::new(),
+            )
+            .unwrap();
+        backend.conn.map_window(number).unwrap();
+        backend.conn.flush().unwrap();
+
+        let window = WindowRef {
+            pid: 0,
+            number: Some(number),
+        };
+        let frame = Rect {
+            x: 20,
+            y: 30,
+            width: 200,
+            height: 150,
+        };
+        for _ in 0..10 {
+            backend.set_frame(&window, &frame).unwrap();
+            if backend.get_frame(&window).unwrap() == frame {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        assert_eq!(backend.get_frame(&window).unwrap(), frame);
+        backend.conn.destroy_window(number).unwrap();
+        backend.conn.flush().unwrap();
+    }
+}
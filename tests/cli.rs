@@ -0,0 +1,95 @@
+//! Runs the binary against the fake backend, the way scripts and CI use it.
+
+use serde_json::{Value, json};
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// The desktop fixture that the unit tests use as well.
+const DESKTOP: &str = include_str!("fixtures/desktop.json");
+
+/// Runs `move_window` with `args`, keeping its config and history in `home`.
+fn run(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_move_window"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_STATE_HOME")
+        .env_remove("MOVE_WINDOW_BACKEND")
+        .output()
+        .unwrap()
+}
+
+fn frame(fixture: &Path) -> Value {
+    let desktop: Value = serde_json::from_str(&std::fs::read_to_string(fixture).unwrap()).unwrap();
+    desktop["windows"][0]["bounds"].clone()
+}
+
+#[test]
+fn moves_and_undoes_on_the_fake_backend() {
+    let home = tempfile::tempdir().unwrap();
+    let fixture = home.path().join("desktop.json");
+    std::fs::write(&fixture, DESKTOP).unwrap();
+    let backend = format!("fake-save:{}", fixture.display());
+
+    let output = run(home.path(), &["--backend", &backend, "move_window", "021"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        frame(&fixture),
+        json!({"x": 960, "y": 25, "width": 960, "height": 1055})
+    );
+
+    let output = run(home.path(), &["--backend", &backend, "undo"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        frame(&fixture),
+        json!({"x": 100, "y": 100, "width": 800, "height": 600})
+    );
+}
+
+#[test]
+fn fake_backend_leaves_the_fixture_alone() {
+    let home = tempfile::tempdir().unwrap();
+    let fixture = home.path().join("desktop.json");
+    std::fs::write(&fixture, DESKTOP).unwrap();
+    let backend = format!("fake:{}", fixture.display());
+
+    let output = run(home.path(), &["--backend", &backend, "move_window", "021"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        frame(&fixture),
+        json!({"x": 100, "y": 100, "width": 800, "height": 600})
+    );
+}
+
+#[test]
+fn parse_errors_exit_with_2() {
+    let home = tempfile::tempdir().unwrap();
+    let fixture = home.path().join("desktop.json");
+    std::fs::write(&fixture, DESKTOP).unwrap();
+    let backend = format!("fake:{}", fixture.display());
+
+    let output = run(home.path(), &["--backend", &backend, "move_window", "0x"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn selection_errors_have_their_own_exit_codes() {
+    let home = tempfile::tempdir().unwrap();
+    let fixture = home.path().join("desktop.json");
+    std::fs::write(&fixture, DESKTOP).unwrap();
+    let backend = format!("fake:{}", fixture.display());
+    let screens = home.path().join("screens.json");
+    let desktop: Value = serde_json::from_str(DESKTOP).unwrap();
+    std::fs::write(&screens, desktop["screens"].to_string()).unwrap();
+    let screens = screens.to_str().unwrap();
+
+    for (args, code) in [
+        (&["move_window", "x"][..], 3),
+        (&["move_window", "5"], 4),
+        (&["move_window", "--screens-file", screens, "s"], 7),
+        (&["move_window", "--window", "app:nope", "021"], 8),
+    ] {
+        let output = run(home.path(), &[&["--backend", &backend], args].concat());
+        assert_eq!(output.status.code(), Some(code), "{:?}: {:?}", args, output);
+    }
+}